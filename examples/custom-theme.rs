@@ -1,5 +1,5 @@
 use log::{Level, LevelFilter};
-use swing::{Color, ColorFormat, Config, Logger, Rgb, RgbRange, Theme};
+use swing::{Color, ColorFormat, ColorRamp, Config, Gradient, Logger, Rgb, RgbRange, Theme};
 
 /// Custom theme
 pub struct MyTheme {}
@@ -67,6 +67,21 @@ impl Theme for MyTheme {
             },
         }
     }
+
+    /// This method returns the colors that gradients move through at each
+    /// level.  By default, it uses the start and end of each level's color
+    /// range, but you can return more stops to make ramps like a heat map.
+    fn ramp(&self, level: Level) -> ColorRamp {
+        match level {
+            Level::Error => ColorRamp::even(&[
+                Color::DarkRed.value(),
+                Color::Red.value(),
+                Color::Orange.value(),
+                Color::Yellow.value(),
+            ]),
+            _ => self.range(level).into(),
+        }
+    }
 }
 
 fn main() {
@@ -74,7 +89,7 @@ fn main() {
     let config = Config {
         level: LevelFilter::Trace,
         theme: Box::new(MyTheme {}),
-        color_format: Some(ColorFormat::InlineGradient(20)),
+        color_format: Some(ColorFormat::InlineGradient(Gradient::new(20))),
         ..Default::default()
     };
     Logger::with_config(config).init().unwrap();
@@ -1,5 +1,5 @@
 use log::LevelFilter;
-use swing::{theme::Spectral, ColorFormat, Config, Logger, RecordFormat};
+use swing::{theme::Spectral, ColorFormat, ColorSpace, Config, Gradient, Logger, RecordFormat};
 mod util;
 
 fn main() {
@@ -7,7 +7,10 @@ fn main() {
     let config = Config {
         level: LevelFilter::Trace,
         record_format: RecordFormat::Simple,
-        color_format: Some(ColorFormat::InlineGradient(200)),
+        color_format: Some(ColorFormat::InlineGradient(Gradient {
+            space: ColorSpace::Oklch,
+            ..Gradient::new(200)
+        })),
         theme: Box::new(Spectral {}),
         ..Default::default()
     };
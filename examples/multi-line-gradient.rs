@@ -1,5 +1,5 @@
 use log::LevelFilter;
-use swing::{theme::Spectral, ColorFormat, Config, Logger, RecordFormat};
+use swing::{theme::Spectral, ColorFormat, Config, Gradient, Logger, RecordFormat};
 mod util;
 
 fn main() {
@@ -7,7 +7,7 @@ fn main() {
     let config = Config {
         level: LevelFilter::Trace,
         record_format: RecordFormat::Simple,
-        color_format: Some(ColorFormat::MultiLineGradient(20)),
+        color_format: Some(ColorFormat::MultiLineGradient(Gradient::new(20))),
         theme: Box::new(Spectral {}),
         ..Default::default()
     };
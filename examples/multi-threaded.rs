@@ -1,14 +1,18 @@
 use log::LevelFilter;
 use std::thread;
-use swing::{theme::Spectral, ColorFormat, Config, Logger};
+use swing::{theme::Spectral, ColorFormat, Config, Gradient, Logger, RecordFields};
 mod util;
 
 fn main() {
     // setup logger
     let config = Config {
         level: LevelFilter::Trace,
-        color_format: Some(ColorFormat::MultiLineGradient(20)),
+        color_format: Some(ColorFormat::MultiLineGradient(Gradient::new(20))),
         theme: Box::new(Spectral {}),
+        record_fields: RecordFields {
+            thread: true,
+            ..Default::default()
+        },
         ..Default::default()
     };
     Logger::with_config(config).init().unwrap();
@@ -16,10 +20,15 @@ fn main() {
     // log sample messages from 10 threads simultaneously
     let mut handles = vec![];
 
-    for _ in 0..10 {
-        handles.push(thread::spawn(move || {
-            util::log_sample_messages(1000);
-        }));
+    for i in 0..10 {
+        handles.push(
+            thread::Builder::new()
+                .name(format!("worker-{}", i))
+                .spawn(move || {
+                    util::log_sample_messages(1000);
+                })
+                .unwrap(),
+        );
     }
 
     for handle in handles {
@@ -0,0 +1,34 @@
+use log::LevelFilter;
+use std::env;
+use swing::{Config, Destination, FileConfig, Logger, RecordFormat, SinkConfig};
+mod util;
+
+fn main() {
+    let path = env::temp_dir().join("swing-sinks-example.log");
+
+    // setup logger with colored output to the console,
+    // and uncolored JSON output (including debug logs) to a file
+    let config = Config {
+        level: LevelFilter::Debug,
+        sinks: vec![
+            SinkConfig {
+                level: LevelFilter::Info,
+                record_format: RecordFormat::Simple,
+                ..Default::default()
+            },
+            SinkConfig {
+                level: LevelFilter::Debug,
+                record_format: RecordFormat::Json,
+                destination: Destination::File(FileConfig::new(&path)),
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    };
+    Logger::with_config(config).init().unwrap();
+
+    // log away!
+    util::log_sample_messages(100);
+
+    println!("JSON logs were written to {}", path.display());
+}
@@ -1,6 +1,11 @@
 //! Color related type definitions and constant values
 
 use colored::{self, Color::TrueColor};
+use serde::de::{self, value::StrDeserializer, Deserializer, Visitor};
+use serde::Deserialize;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::OnceLock;
 
 /// RGB triplet
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
@@ -13,13 +18,510 @@ pub struct Rgb {
     pub b: u8,
 }
 
-impl Into<colored::Color> for Rgb {
+impl From<Rgb> for colored::Color {
     /// Convert Rgb -> Color for easier use with string coloring
-    fn into(self) -> colored::Color {
+    fn from(rgb: Rgb) -> colored::Color {
         TrueColor {
-            r: self.r,
-            g: self.g,
-            b: self.b,
+            r: rgb.r,
+            g: rgb.g,
+            b: rgb.b,
+        }
+    }
+}
+
+/// Visitor that parses an `Rgb` from a `#rrggbb` hex string or a `Color` name
+struct RgbVisitor;
+
+impl<'de> Visitor<'de> for RgbVisitor {
+    type Value = Rgb;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a `#rrggbb` hex color or a color name (e.g. `dark_red`)")
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Rgb, E> {
+        let hex = match s.strip_prefix('#') {
+            Some(hex) => hex,
+            None => return Ok(Color::deserialize(StrDeserializer::<E>::new(s))?.value()),
+        };
+
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+                .ok_or_else(|| E::invalid_value(de::Unexpected::Str(s), &self))
+        };
+
+        if hex.len() != 6 {
+            return Err(E::invalid_value(de::Unexpected::Str(s), &self));
+        }
+
+        Ok(Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Rgb {
+    /// Deserialize an `Rgb` from a `#rrggbb` hex string or a `Color` name in snake case
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Rgb, D::Error> {
+        deserializer.deserialize_str(RgbVisitor)
+    }
+}
+
+/// Default colors of the 16 standard ANSI colors in xterm, indexed by color number
+const ANSI16_PALETTE: [Rgb; 16] = [
+    Rgb { r: 0, g: 0, b: 0 },
+    Rgb { r: 205, g: 0, b: 0 },
+    Rgb { r: 0, g: 205, b: 0 },
+    Rgb {
+        r: 205,
+        g: 205,
+        b: 0,
+    },
+    Rgb { r: 0, g: 0, b: 238 },
+    Rgb {
+        r: 205,
+        g: 0,
+        b: 205,
+    },
+    Rgb {
+        r: 0,
+        g: 205,
+        b: 205,
+    },
+    Rgb {
+        r: 229,
+        g: 229,
+        b: 229,
+    },
+    Rgb {
+        r: 127,
+        g: 127,
+        b: 127,
+    },
+    Rgb { r: 255, g: 0, b: 0 },
+    Rgb { r: 0, g: 255, b: 0 },
+    Rgb {
+        r: 255,
+        g: 255,
+        b: 0,
+    },
+    Rgb {
+        r: 92,
+        g: 92,
+        b: 255,
+    },
+    Rgb {
+        r: 255,
+        g: 0,
+        b: 255,
+    },
+    Rgb {
+        r: 0,
+        g: 255,
+        b: 255,
+    },
+    Rgb {
+        r: 255,
+        g: 255,
+        b: 255,
+    },
+];
+
+/// Intensities of each channel in the 6x6x6 color cube of the xterm 256 color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// OKLab coordinates of the xterm 256 color palette, indexed by color number
+static ANSI256_OKLAB: OnceLock<Vec<[f32; 3]>> = OnceLock::new();
+
+/// Convert an sRGB channel to linear light
+///
+/// # Arguments
+///
+/// * `c` - sRGB channel intensity
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Convert a linear light channel to sRGB
+///
+/// # Arguments
+///
+/// * `c` - linear light channel intensity (0.0 - 1.0)
+fn linear_to_srgb(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let c = if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+
+    (c * 255.0).round() as u8
+}
+
+/// Interpolate between two hues (in degrees) along the shorter way around the color wheel
+///
+/// # Arguments
+///
+/// * `a` - start hue
+/// * `b` - end hue
+/// * `t` - distance from `a` to `b` (0.0 - 1.0)
+fn mix_hue(a: f32, b: f32, t: f32) -> f32 {
+    let mut delta = (b - a).rem_euclid(360.0);
+
+    if delta > 180.0 {
+        delta -= 360.0;
+    }
+
+    (a + delta * t).rem_euclid(360.0)
+}
+
+/// Interpolate between two colors in a cylindrical color space (`[hue, x, y]`), taking
+/// the hue of the other color when one of them has no hue (e.g. gray)
+///
+/// # Arguments
+///
+/// * `a` - start color
+/// * `b` - end color
+/// * `t` - distance from `a` to `b` (0.0 - 1.0)
+/// * `chroma` - index of the coordinate that is zero for colors without a hue
+fn mix_polar(a: [f32; 3], b: [f32; 3], t: f32, chroma: usize) -> [f32; 3] {
+    let (ha, hb) = match (a[chroma] < 1e-4, b[chroma] < 1e-4) {
+        (true, false) => (b[0], b[0]),
+        (false, true) => (a[0], a[0]),
+        _ => (a[0], b[0]),
+    };
+
+    [
+        mix_hue(ha, hb, t),
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+    ]
+}
+
+/// Get the squared perceptual distance between two colors in OKLab
+///
+/// # Arguments
+///
+/// * `lhs` - first color's OKLab coordinates
+/// * `rhs` - second color's OKLab coordinates
+/// * `lightness_weight` - weight of the difference in lightness, relative to hue and chroma
+fn oklab_dist_sq(lhs: &[f32; 3], rhs: &[f32; 3], lightness_weight: f32) -> f32 {
+    let l = (lhs[0] - rhs[0]) * lightness_weight;
+    let a = lhs[1] - rhs[1];
+    let b = lhs[2] - rhs[2];
+
+    l * l + a * a + b * b
+}
+
+/// Color space that gradients interpolate colors in
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorSpace {
+    /// interpolate sRGB values directly (cheapest, but midpoints can be dark or muddy)
+    #[default]
+    Srgb,
+    /// interpolate linear light RGB values, which keeps midpoints bright
+    LinearRgb,
+    /// interpolate hue (the shorter way around the color wheel), saturation, and lightness
+    Hsl,
+    /// interpolate hue (the shorter way around the color wheel), saturation, and value
+    Hsv,
+    /// interpolate in the OKLab perceptual color space, for even steps in lightness
+    Oklab,
+    /// interpolate lightness, chroma, and hue (the shorter way around the color wheel) in
+    /// OKLab's cylindrical form, for vivid midpoints between different hues
+    Oklch,
+}
+
+impl Rgb {
+    /// Get the color at number `n` of the xterm 256 color palette (0 - 15 are the
+    /// standard ANSI colors, 16 - 231 a 6x6x6 color cube, and 232 - 255 a grayscale ramp)
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - palette color number
+    pub fn from_ansi256(n: u8) -> Rgb {
+        match n {
+            0..=15 => ANSI16_PALETTE[n as usize],
+            16..=231 => {
+                let n = (n - 16) as usize;
+                Rgb {
+                    r: CUBE_LEVELS[n / 36],
+                    g: CUBE_LEVELS[n / 6 % 6],
+                    b: CUBE_LEVELS[n % 6],
+                }
+            }
+            _ => {
+                let v = 8 + (n - 232) * 10;
+                Rgb { r: v, g: v, b: v }
+            }
+        }
+    }
+
+    /// Get the coordinates of this color in the OKLab perceptual color space
+    pub(crate) fn to_oklab(self) -> [f32; 3] {
+        let (r, g, b) = (
+            srgb_to_linear(self.r),
+            srgb_to_linear(self.g),
+            srgb_to_linear(self.b),
+        );
+
+        let l = (0.412_221_47 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+        let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+        let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+
+        [
+            0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+            1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+            0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+        ]
+    }
+
+    /// Get the color at OKLab coordinates, clamping colors outside of the sRGB gamut
+    ///
+    /// # Arguments
+    ///
+    /// * `lab` - OKLab coordinates
+    pub(crate) fn from_oklab(lab: [f32; 3]) -> Rgb {
+        let [l, a, b] = lab;
+        let l_ = (l + 0.396_337_78 * a + 0.215_803_76 * b).powi(3);
+        let m_ = (l - 0.105_561_346 * a - 0.063_854_17 * b).powi(3);
+        let s_ = (l - 0.089_484_18 * a - 1.291_485_5 * b).powi(3);
+
+        Rgb {
+            r: linear_to_srgb(4.076_741_7 * l_ - 3.307_711_6 * m_ + 0.230_969_94 * s_),
+            g: linear_to_srgb(-1.268_438 * l_ + 2.609_757_4 * m_ - 0.341_319_38 * s_),
+            b: linear_to_srgb(-0.004_196_086_3 * l_ - 0.703_418_6 * m_ + 1.707_614_7 * s_),
+        }
+    }
+
+    /// Get the hue (in degrees), saturation, and lightness of this color
+    fn to_hsl(self) -> [f32; 3] {
+        let (r, g, b) = (
+            self.r as f32 / 255.0,
+            self.g as f32 / 255.0,
+            self.b as f32 / 255.0,
+        );
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let l = (max + min) / 2.0;
+        let d = max - min;
+
+        if d == 0.0 {
+            return [0.0, 0.0, l];
+        }
+
+        let s = d / (1.0 - (2.0 * l - 1.0).abs());
+        let h = if max == r {
+            60.0 * ((g - b) / d).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / d + 2.0)
+        } else {
+            60.0 * ((r - g) / d + 4.0)
+        };
+
+        [h, s, l]
+    }
+
+    /// Get the color with a hue, chroma, and minimum channel intensity (shared by the HSL
+    /// and HSV conversions)
+    ///
+    /// # Arguments
+    ///
+    /// * `h` - hue, in degrees
+    /// * `c` - chroma (0.0 - 1.0)
+    /// * `m` - intensity of the weakest channel (0.0 - 1.0)
+    fn from_hue_chroma(h: f32, c: f32, m: f32) -> Rgb {
+        let h = h.rem_euclid(360.0) / 60.0;
+        let x = c * (1.0 - (h.rem_euclid(2.0) - 1.0).abs());
+        let (r, g, b) = match h as u8 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        let channel = |v: f32| ((v + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+
+        Rgb {
+            r: channel(r),
+            g: channel(g),
+            b: channel(b),
+        }
+    }
+
+    /// Get the color with a hue (in degrees), saturation, and lightness
+    ///
+    /// # Arguments
+    ///
+    /// * `hsl` - hue, saturation, and lightness
+    fn from_hsl(hsl: [f32; 3]) -> Rgb {
+        let [h, s, l] = hsl;
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+
+        Rgb::from_hue_chroma(h, c, l - c / 2.0)
+    }
+
+    /// Get the hue (in degrees), saturation, and value of this color
+    fn to_hsv(self) -> [f32; 3] {
+        let [h, _, _] = self.to_hsl();
+        let max = self.r.max(self.g).max(self.b) as f32 / 255.0;
+        let min = self.r.min(self.g).min(self.b) as f32 / 255.0;
+        let s = if max == 0.0 { 0.0 } else { (max - min) / max };
+
+        [h, s, max]
+    }
+
+    /// Get the color with a hue (in degrees), saturation, and value
+    ///
+    /// # Arguments
+    ///
+    /// * `hsv` - hue, saturation, and value
+    fn from_hsv(hsv: [f32; 3]) -> Rgb {
+        let [h, s, v] = hsv;
+        let c = v * s;
+
+        Rgb::from_hue_chroma(h, c, v - c)
+    }
+
+    /// Get the hue (in degrees), chroma, and lightness of this color in OKLCh
+    fn to_oklch(self) -> [f32; 3] {
+        let [l, a, b] = self.to_oklab();
+
+        [b.atan2(a).to_degrees().rem_euclid(360.0), a.hypot(b), l]
+    }
+
+    /// Get the color with a hue (in degrees), chroma, and lightness in OKLCh
+    ///
+    /// # Arguments
+    ///
+    /// * `lch` - hue, chroma, and lightness
+    fn from_oklch(lch: [f32; 3]) -> Rgb {
+        let [h, c, l] = lch;
+        let h = h.to_radians();
+
+        Rgb::from_oklab([l, c * h.cos(), c * h.sin()])
+    }
+
+    /// Get the color `t` of the way from this color to `other`
+    ///
+    /// `t` will be clamped to the range 0.0 - 1.0
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - color at the end of the interpolation
+    /// * `t` - distance from this color to `other` (0.0 - 1.0)
+    /// * `space` - color space to interpolate in
+    pub fn mix(&self, other: &Rgb, t: f32, space: ColorSpace) -> Rgb {
+        let t = t.clamp(0.0, 1.0);
+        let lerp = |a: [f32; 3], b: [f32; 3]| {
+            [
+                a[0] + (b[0] - a[0]) * t,
+                a[1] + (b[1] - a[1]) * t,
+                a[2] + (b[2] - a[2]) * t,
+            ]
+        };
+
+        match space {
+            ColorSpace::Srgb => {
+                let [r, g, b] = lerp(
+                    [self.r as f32, self.g as f32, self.b as f32],
+                    [other.r as f32, other.g as f32, other.b as f32],
+                );
+                Rgb {
+                    r: r.round() as u8,
+                    g: g.round() as u8,
+                    b: b.round() as u8,
+                }
+            }
+            ColorSpace::LinearRgb => {
+                let linear = |c: &Rgb| {
+                    [
+                        srgb_to_linear(c.r),
+                        srgb_to_linear(c.g),
+                        srgb_to_linear(c.b),
+                    ]
+                };
+                let [r, g, b] = lerp(linear(self), linear(other));
+                Rgb {
+                    r: linear_to_srgb(r),
+                    g: linear_to_srgb(g),
+                    b: linear_to_srgb(b),
+                }
+            }
+            ColorSpace::Hsl => Rgb::from_hsl(mix_polar(self.to_hsl(), other.to_hsl(), t, 1)),
+            ColorSpace::Hsv => Rgb::from_hsv(mix_polar(self.to_hsv(), other.to_hsv(), t, 1)),
+            ColorSpace::Oklab => Rgb::from_oklab(lerp(self.to_oklab(), other.to_oklab())),
+            ColorSpace::Oklch => {
+                Rgb::from_oklch(mix_polar(self.to_oklch(), other.to_oklch(), t, 1))
+            }
+        }
+    }
+
+    /// Get the number of the perceptually nearest color in a range of the xterm 256
+    /// color palette
+    ///
+    /// # Arguments
+    ///
+    /// * `colors` - palette color numbers to search
+    /// * `lightness_weight` - weight of the difference in lightness, relative to hue and
+    ///   chroma
+    fn nearest_ansi(&self, colors: RangeInclusive<u8>, lightness_weight: f32) -> u8 {
+        let palette = ANSI256_OKLAB
+            .get_or_init(|| (0..=255).map(|n| Rgb::from_ansi256(n).to_oklab()).collect());
+        let lab = self.to_oklab();
+
+        colors
+            .min_by(|&a, &b| {
+                let dist = |n: u8| oklab_dist_sq(&lab, &palette[n as usize], lightness_weight);
+                dist(a).total_cmp(&dist(b))
+            })
+            .unwrap_or(0)
+    }
+
+    /// Get the number (0 - 15) of the perceptually nearest standard ANSI color
+    ///
+    /// Differences in hue count for more than differences in lightness, since the few
+    /// standard colors are far apart and muted colors would otherwise all map to gray
+    pub fn to_ansi16(&self) -> u8 {
+        self.nearest_ansi(0..=15, 0.5)
+    }
+
+    /// Get the number (16 - 255) of the perceptually nearest color in the xterm 256 color
+    /// palette's color cube or grayscale ramp
+    ///
+    /// The standard ANSI colors (0 - 15) are skipped, since terminals often change them
+    pub fn to_ansi256(&self) -> u8 {
+        self.nearest_ansi(16..=255, 1.0)
+    }
+
+    /// Get the perceived brightness of this color (0.0 for black - 1.0 for white), as the
+    /// Rec. 601 luma of its sRGB channels
+    pub fn luma(&self) -> f32 {
+        (0.299 * self.r as f32 + 0.587 * self.g as f32 + 0.114 * self.b as f32) / 255.0
+    }
+
+    /// Get black or white, whichever stands out more against this color (e.g. for text on a
+    /// background of this color)
+    pub fn contrasting(&self) -> Rgb {
+        if self.luma() > 0.5 {
+            Rgb { r: 0, g: 0, b: 0 }
+        } else {
+            Rgb {
+                r: 255,
+                g: 255,
+                b: 255,
+            }
         }
     }
 }
@@ -34,12 +536,128 @@ pub struct RgbRange {
     pub end: Rgb,
 }
 
+impl RgbRange {
+    /// Get the color `t` of the way from the start to the end of this range
+    ///
+    /// # Arguments
+    ///
+    /// * `t` - distance along the range (0.0 - 1.0)
+    /// * `space` - color space to interpolate in
+    pub fn sample(&self, t: f32, space: ColorSpace) -> Rgb {
+        self.start.mix(&self.end, t, space)
+    }
+}
+
+/// Color at a position along a `ColorRamp`
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct ColorStop {
+    /// position of this stop along the ramp (0.0 - 1.0)
+    pub position: f32,
+    /// color at this stop
+    pub color: Rgb,
+}
+
+/// Multi-stop color gradient (e.g. a heat map going from blue to green to yellow to red),
+/// interpolating between neighboring stops
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct ColorRamp {
+    /// color stops, ordered by position
+    stops: Vec<ColorStop>,
+}
+
+impl ColorRamp {
+    /// Create a new ColorRamp
+    ///
+    /// # Arguments
+    ///
+    /// * `stops` - color stops, in any order (positions are clamped to 0.0 - 1.0)
+    pub fn new(stops: Vec<ColorStop>) -> ColorRamp {
+        let mut stops: Vec<ColorStop> = stops
+            .into_iter()
+            .map(|s| ColorStop {
+                position: s.position.clamp(0.0, 1.0),
+                ..s
+            })
+            .collect();
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+
+        ColorRamp { stops }
+    }
+
+    /// Create a new ColorRamp with colors spaced evenly from 0.0 to 1.0
+    ///
+    /// # Arguments
+    ///
+    /// * `colors` - colors of the stops, in order
+    pub fn even(colors: &[Rgb]) -> ColorRamp {
+        let last = colors.len().saturating_sub(1).max(1) as f32;
+
+        ColorRamp {
+            stops: colors
+                .iter()
+                .enumerate()
+                .map(|(i, c)| ColorStop {
+                    position: i as f32 / last,
+                    color: *c,
+                })
+                .collect(),
+        }
+    }
+
+    /// Get the color stops of this ramp, ordered by position
+    pub fn stops(&self) -> &[ColorStop] {
+        &self.stops
+    }
+
+    /// Get the color at position `t` along this ramp
+    ///
+    /// Positions before the first stop or after the last stop take that stop's color
+    ///
+    /// # Arguments
+    ///
+    /// * `t` - position along the ramp (0.0 - 1.0)
+    /// * `space` - color space to interpolate between stops in
+    pub fn sample(&self, t: f32, space: ColorSpace) -> Rgb {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Rgb::default(),
+        };
+
+        if t <= first.position {
+            return first.color;
+        }
+
+        // stops on either side of `t`
+        match self.stops.windows(2).find(|w| t <= w[1].position) {
+            Some(w) => {
+                let span = w[1].position - w[0].position;
+                let t = if span > 0.0 {
+                    (t - w[0].position) / span
+                } else {
+                    1.0
+                };
+                w[0].color.mix(&w[1].color, t, space)
+            }
+            None => last.color,
+        }
+    }
+}
+
+impl From<RgbRange> for ColorRamp {
+    /// Convert RgbRange -> ColorRamp with stops at the start and end of the range
+    fn from(range: RgbRange) -> ColorRamp {
+        ColorRamp::even(&[range.start, range.end])
+    }
+}
+
 /// Constant predefined color values
 ///
 /// Use of these color values is not necessary when
 /// creating a theme, but they act as helpful aliases
 /// for raw Rgb triplets
 #[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Color {
     /// dark magenta
     DarkMagenta,
@@ -202,4 +820,265 @@ mod tests {
             assert_eq!(c, tc);
         }
     }
+
+    #[test]
+    fn rgb_from_ansi256_matches_xterm_palette() {
+        assert_eq!(Rgb::from_ansi256(9), Rgb { r: 255, g: 0, b: 0 });
+        assert_eq!(Rgb::from_ansi256(16), Rgb { r: 0, g: 0, b: 0 });
+        assert_eq!(
+            Rgb::from_ansi256(173),
+            Rgb {
+                r: 215,
+                g: 135,
+                b: 95
+            }
+        );
+        assert_eq!(
+            Rgb::from_ansi256(244),
+            Rgb {
+                r: 128,
+                g: 128,
+                b: 128
+            }
+        );
+        assert_eq!(
+            Rgb::from_ansi256(255),
+            Rgb {
+                r: 238,
+                g: 238,
+                b: 238
+            }
+        );
+
+        // every palette color maps back to itself
+        for n in 16..=255 {
+            assert_eq!(Rgb::from_ansi256(n).to_ansi256(), n);
+        }
+    }
+
+    #[test]
+    fn rgb_mix_finds_known_midpoints() {
+        let black = Rgb { r: 0, g: 0, b: 0 };
+        let white = Rgb {
+            r: 255,
+            g: 255,
+            b: 255,
+        };
+        let red = Rgb { r: 255, g: 0, b: 0 };
+        let blue = Rgb { r: 0, g: 0, b: 255 };
+        let gray = |v: u8| Rgb { r: v, g: v, b: v };
+
+        assert_eq!(black.mix(&white, 0.5, ColorSpace::Srgb), gray(128));
+        assert_eq!(black.mix(&white, 0.5, ColorSpace::LinearRgb), gray(188));
+        // OKLab lightness 0.5 is about 39% sRGB intensity
+        assert_eq!(black.mix(&white, 0.5, ColorSpace::Oklab), gray(99));
+
+        // hues take the shorter way around the color wheel (red 0° -> blue 240° via 300°)
+        let magenta = Rgb {
+            r: 255,
+            g: 0,
+            b: 255,
+        };
+        assert_eq!(red.mix(&blue, 0.5, ColorSpace::Hsl), magenta);
+        assert_eq!(red.mix(&blue, 0.5, ColorSpace::Hsv), magenta);
+        assert_eq!(
+            red.mix(&blue, 0.5, ColorSpace::Srgb),
+            Rgb {
+                r: 128,
+                g: 0,
+                b: 128
+            }
+        );
+        assert_eq!(
+            red.mix(&blue, 0.5, ColorSpace::Oklab),
+            Rgb {
+                r: 140,
+                g: 83,
+                b: 162
+            }
+        );
+        assert_eq!(
+            red.mix(&blue, 0.5, ColorSpace::Oklch),
+            Rgb {
+                r: 186,
+                g: 0,
+                b: 194
+            }
+        );
+
+        // gray has no hue, so only saturation and lightness change
+        assert_eq!(
+            gray(128).mix(&red, 0.5, ColorSpace::Hsl),
+            Rgb {
+                r: 191,
+                g: 64,
+                b: 64
+            }
+        );
+    }
+
+    #[test]
+    fn rgb_mix_keeps_endpoints() {
+        let start = Color::DarkRed.value();
+        let end = Color::Cyan.value();
+
+        for space in [
+            ColorSpace::Srgb,
+            ColorSpace::LinearRgb,
+            ColorSpace::Hsl,
+            ColorSpace::Hsv,
+            ColorSpace::Oklab,
+            ColorSpace::Oklch,
+        ] {
+            let eq = |a: Rgb, b: Rgb| {
+                a.r.abs_diff(b.r) <= 1 && a.g.abs_diff(b.g) <= 1 && a.b.abs_diff(b.b) <= 1
+            };
+            assert!(eq(start.mix(&end, 0.0, space), start), "{:?}", space);
+            assert!(eq(start.mix(&end, 1.0, space), end), "{:?}", space);
+            assert!(eq(start.mix(&end, -1.0, space), start), "{:?}", space);
+        }
+    }
+
+    #[test]
+    fn color_ramp_samples_between_stops() {
+        let red = Rgb { r: 255, g: 0, b: 0 };
+        let yellow = Rgb {
+            r: 255,
+            g: 255,
+            b: 0,
+        };
+        let blue = Rgb { r: 0, g: 0, b: 255 };
+        let ramp = ColorRamp::new(vec![
+            ColorStop {
+                position: 1.0,
+                color: red,
+            },
+            ColorStop {
+                position: 0.25,
+                color: blue,
+            },
+            ColorStop {
+                position: 0.75,
+                color: yellow,
+            },
+        ]);
+        let sample = |t: f32| ramp.sample(t, ColorSpace::Srgb);
+
+        assert_eq!(ramp.stops()[0].color, blue);
+        assert_eq!(sample(0.0), blue);
+        assert_eq!(sample(0.25), blue);
+        assert_eq!(
+            sample(0.5),
+            Rgb {
+                r: 128,
+                g: 128,
+                b: 128
+            }
+        );
+        assert_eq!(sample(0.75), yellow);
+        assert_eq!(
+            sample(0.875),
+            Rgb {
+                r: 255,
+                g: 128,
+                b: 0
+            }
+        );
+        assert_eq!(sample(1.0), red);
+        assert_eq!(sample(2.0), red);
+    }
+
+    #[test]
+    fn color_ramp_handles_few_stops() {
+        let red = Rgb { r: 255, g: 0, b: 0 };
+
+        assert_eq!(
+            ColorRamp::default().sample(0.5, ColorSpace::Srgb),
+            Rgb::default()
+        );
+        assert_eq!(ColorRamp::even(&[red]).sample(0.5, ColorSpace::Srgb), red);
+
+        let range = RgbRange {
+            start: Rgb::default(),
+            end: red,
+        };
+        let ramp = ColorRamp::from(range);
+        for t in [0.0, 0.3, 0.5, 1.0] {
+            assert_eq!(
+                ramp.sample(t, ColorSpace::Oklab),
+                range.sample(t, ColorSpace::Oklab)
+            );
+        }
+    }
+
+    #[test]
+    fn rgb_to_ansi16_finds_nearest_color() {
+        assert_eq!(Rgb { r: 0, g: 0, b: 0 }.to_ansi16(), 0);
+        assert_eq!(Color::DarkRed.value().to_ansi16(), 1);
+        assert_eq!(Color::Cyan.value().to_ansi16(), 6);
+        // nearer to yellow than green by RGB distance, but perceptually green
+        assert_eq!(Color::DarkGreen.value().to_ansi16(), 2);
+        assert_eq!(Color::Magenta.value().to_ansi16(), 13);
+        assert_eq!(
+            Rgb {
+                r: 250,
+                g: 250,
+                b: 250
+            }
+            .to_ansi16(),
+            15
+        );
+    }
+
+    #[test]
+    fn rgb_to_ansi256_finds_nearest_color() {
+        assert_eq!(Rgb { r: 0, g: 0, b: 0 }.to_ansi256(), 16);
+        assert_eq!(Color::Magenta.value().to_ansi256(), 201);
+        assert_eq!(
+            Rgb {
+                r: 255,
+                g: 255,
+                b: 255
+            }
+            .to_ansi256(),
+            231
+        );
+        assert_eq!(
+            Rgb {
+                r: 95,
+                g: 135,
+                b: 0
+            }
+            .to_ansi256(),
+            64
+        );
+        assert_eq!(
+            Rgb {
+                r: 128,
+                g: 128,
+                b: 128
+            }
+            .to_ansi256(),
+            244
+        );
+    }
+
+    #[test]
+    fn contrasting_picks_readable_text_color() {
+        let black = Rgb { r: 0, g: 0, b: 0 };
+        let white = Rgb {
+            r: 255,
+            g: 255,
+            b: 255,
+        };
+
+        assert_eq!(black.luma(), 0.0);
+        assert_eq!(white.luma(), 1.0);
+        assert_eq!(black.contrasting(), white);
+        assert_eq!(white.contrasting(), black);
+        assert_eq!(Color::DarkRed.value().contrasting(), white);
+        assert_eq!(Color::Red.value().contrasting(), white);
+        assert_eq!(Color::Yellow.value().contrasting(), black);
+        assert_eq!(Color::Green.value().contrasting(), black);
+    }
 }
@@ -1,12 +1,32 @@
 //! Configuration related definitions and implementation
 
-use crate::{paint::ColorFormat, sculpt::RecordFormat, theme::Spectral, theme::Theme};
+use crate::{
+    description::{ConfigDescription, ConfigError, ConfigFormat},
+    filter::{Directive, FilterError, FilterSpec},
+    paint::{ColorFormat, Quantization},
+    queue::AsyncConfig,
+    sculpt::{RecordFields, RecordFormat},
+    sink::SinkConfig,
+    term::ColorSupport,
+    theme::Spectral,
+    theme::Theme,
+    timestamp::TimestampConfig,
+    write::Destination,
+};
 use log::LevelFilter;
+use regex::Regex;
+use std::path::Path;
+use std::{env, fs};
 
 /// Main configuration for a `Logger`
 pub struct Config {
     /// log level filter (logs below this severity will be ignored)
     pub level: LevelFilter,
+    /// per-target log level filters (for each record, the directive with the longest
+    /// matching target prefix overrides `level`)
+    pub directives: Vec<Directive>,
+    /// regular expression that log messages must match to be logged (`None` logs all messages)
+    pub message_filter: Option<Regex>,
     /// record formatting mode (determines how log records are structurally formatted)
     pub record_format: RecordFormat,
     /// color formatting mode (determines how log records are colored)
@@ -19,6 +39,34 @@ pub struct Config {
     ///
     /// - `false`: log all levels to `stdout`
     pub use_stderr: bool,
+    /// output destination for log records (console or file)
+    pub destination: Destination,
+    /// colors supported by console output, overriding detection (`None` detects them for
+    /// stdout and stderr separately, disabling colors for streams that aren't terminals
+    /// and downgrading them to 256 or 16 colors when the terminal lacks 24-bit color)
+    pub color_support: Option<ColorSupport>,
+    /// how colors are quantized to the 256 or 16 color palettes of terminals without
+    /// 24-bit color
+    pub quantization: Quantization,
+    /// output sinks, each with its own level, formats, theme, and destination
+    ///
+    /// If empty, records are written to a single sink made from `record_format`,
+    /// `color_format`, `theme`, `use_stderr`, `destination`, `color_support`, and
+    /// `quantization`.  Otherwise, those settings are unused and records are written to
+    /// each of these sinks instead.
+    pub sinks: Vec<SinkConfig>,
+    /// settings for writing records on a background thread (`None` writes records on the
+    /// thread that logs them)
+    pub async_writer: Option<AsyncConfig>,
+    /// key to nest structured key-values under in `Json` records (`None` adds them as
+    /// top-level fields)
+    pub json_kv_key: Option<String>,
+    /// timestamp offset and format used by the built-in record formats (`None` omits
+    /// timestamps, e.g. when running under systemd, which adds its own)
+    pub timestamp: Option<TimestampConfig>,
+    /// optional record fields (source file and line, module path, and thread) included by
+    /// the `Json` and `Simple` record formats
+    pub record_fields: RecordFields,
 }
 
 impl Default for Config {
@@ -26,10 +74,136 @@ impl Default for Config {
     fn default() -> Config {
         Config {
             level: LevelFilter::Info,
+            directives: vec![],
+            message_filter: None,
             record_format: RecordFormat::Simple,
             color_format: Some(ColorFormat::Solid),
             theme: Box::new(Spectral {}),
             use_stderr: true,
+            destination: Destination::Console,
+            color_support: None,
+            quantization: Quantization::Nearest,
+            sinks: vec![],
+            async_writer: None,
+            json_kv_key: None,
+            timestamp: Some(TimestampConfig::default()),
+            record_fields: RecordFields::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Create a `Config` with level, target and message filters read from an
+    /// `env_logger` style filter string in the environment variable `var`
+    /// (e.g. `RUST_LOG=info,my_crate::net=trace,hyper=off`)
+    ///
+    /// Settings other than filters use their default values.  If `var` is not set,
+    /// the default configuration is returned.  See `FilterSpec` for the filter string syntax.
+    ///
+    /// # Arguments
+    ///
+    /// * `var` - name of the environment variable to read filters from
+    pub fn from_env(var: &str) -> Result<Config, FilterError> {
+        let spec = match env::var(var) {
+            Ok(spec) => spec,
+            Err(env::VarError::NotPresent) => return Ok(Config::default()),
+            Err(env::VarError::NotUnicode(_)) => {
+                return Err(FilterError::NotUnicode(var.to_string()))
+            }
+        };
+
+        Ok(Config::default().with_filters(spec.parse()?))
+    }
+
+    /// Load a `Config` from a TOML or JSON file, choosing the format by the file's
+    /// extension (see `ConfigDescription` for the keys)
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path of a `.toml` or `.json` configuration file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
+        let format = ConfigFormat::from_path(path)
+            .ok_or_else(|| ConfigError::UnknownFormat(path.to_path_buf()))?;
+        let s = fs::read_to_string(path).map_err(|error| ConfigError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+
+        Config::parse(&s, format).map_err(|e| match e {
+            ConfigError::Invalid {
+                key, line, reason, ..
+            } => ConfigError::Invalid {
+                path: Some(path.to_path_buf()),
+                key,
+                line,
+                reason,
+            },
+            e => e,
+        })
+    }
+
+    /// Load a `Config` from TOML or JSON text (see `ConfigDescription` for the keys)
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - the configuration text
+    /// * `format` - format of the configuration text
+    pub fn parse(s: &str, format: ConfigFormat) -> Result<Config, ConfigError> {
+        Ok(ConfigDescription::parse(s, format)?.into())
+    }
+
+    /// Replace the level, target and message filters in this `Config` with those in `spec`
+    ///
+    /// # Arguments
+    ///
+    /// * `spec` - parsed filter string
+    pub fn with_filters(self, spec: FilterSpec) -> Config {
+        Config {
+            level: spec.default_level(self.level),
+            directives: spec.directives,
+            message_filter: spec.message_filter,
+            ..self
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_env_without_var_is_default() {
+        let config = Config::from_env("SWING_TEST_FROM_ENV_UNSET").unwrap();
+
+        assert_eq!(config.level, LevelFilter::Info);
+        assert!(config.directives.is_empty());
+        assert!(config.message_filter.is_none());
+    }
+
+    #[test]
+    fn from_env_parses_filters() {
+        env::set_var("SWING_TEST_FROM_ENV_SET", "debug,hyper=off/foo");
+        let config = Config::from_env("SWING_TEST_FROM_ENV_SET").unwrap();
+
+        assert_eq!(config.level, LevelFilter::Debug);
+        assert_eq!(
+            config.directives,
+            vec![Directive {
+                target: "hyper".to_string(),
+                level: LevelFilter::Off,
+            }]
+        );
+        assert_eq!(config.message_filter.unwrap().as_str(), "foo");
+    }
+
+    #[test]
+    fn from_env_reports_malformed_filters() {
+        env::set_var("SWING_TEST_FROM_ENV_BAD", "hyper=loud");
+
+        assert!(matches!(
+            Config::from_env("SWING_TEST_FROM_ENV_BAD"),
+            Err(FilterError::InvalidLevel { .. })
+        ));
+    }
+}
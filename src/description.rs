@@ -0,0 +1,928 @@
+//! Logger configurations described in TOML or JSON files
+//!
+//! A `ConfigDescription` holds everything in a `Config` that can be written down in a
+//! file: named themes or inline theme colors instead of `Box<dyn Theme>`, and the
+//! built-in record formats instead of closures
+
+use crate::{
+    color::{ColorRamp, Rgb, RgbRange},
+    filter::Directive,
+    paint::{ColorFormat, Gradient, HashKey, Quantization},
+    pattern::Pattern,
+    queue::AsyncConfig,
+    sculpt::{RecordFields, RecordFormat, Segment},
+    sink::SinkConfig,
+    style::Style,
+    syslog::SyslogConfig,
+    term::ColorSupport,
+    theme::{self, Theme},
+    timestamp::{Clock, TimeOffset, TimestampConfig, TimestampFormat},
+    write::Destination,
+    Config,
+};
+use log::{Level, LevelFilter};
+use regex::Regex;
+use serde::{de, Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::{fmt, io};
+
+/// Format of a configuration file
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum ConfigFormat {
+    /// TOML (`.toml` files)
+    Toml,
+    /// JSON (`.json` files)
+    Json,
+}
+
+impl ConfigFormat {
+    /// Get the format of a configuration file from its extension, if it is `.toml` or
+    /// `.json`
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path of the configuration file
+    pub fn from_path(path: &Path) -> Option<ConfigFormat> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "toml" => Some(ConfigFormat::Toml),
+            "json" => Some(ConfigFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Error returned when a configuration file can't be read or parsed
+#[derive(Debug)]
+pub enum ConfigError {
+    /// the configuration file couldn't be read
+    Io {
+        /// path of the configuration file
+        path: PathBuf,
+        /// error returned while reading the file
+        error: io::Error,
+    },
+    /// the configuration file's extension isn't `.toml` or `.json`
+    UnknownFormat(PathBuf),
+    /// the configuration has invalid syntax, or a key with an invalid value
+    Invalid {
+        /// path of the configuration file (`None` for configurations parsed from text)
+        path: Option<PathBuf>,
+        /// path of the invalid key (e.g. `sinks[0].level`), empty for syntax errors
+        /// outside of any key
+        key: String,
+        /// line of the error, if known
+        line: Option<usize>,
+        /// description of what is wrong
+        reason: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, error } => {
+                write!(
+                    f,
+                    "failed to read config file {}: {}",
+                    path.display(),
+                    error
+                )
+            }
+            ConfigError::UnknownFormat(path) => write!(
+                f,
+                "unknown format of config file {} (expected a .toml or .json file)",
+                path.display()
+            ),
+            ConfigError::Invalid {
+                path,
+                key,
+                line,
+                reason,
+            } => {
+                match path {
+                    Some(path) => write!(f, "invalid config file {}", path.display())?,
+                    None => write!(f, "invalid config")?,
+                }
+
+                if !key.is_empty() {
+                    write!(f, " at `{}`", key)?;
+                }
+
+                if let Some(line) = line {
+                    write!(f, " (line {})", line)?;
+                }
+
+                write!(f, ": {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Deserialize a message filter, checking that it is a valid regular expression
+fn message_filter<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Regex>, D::Error> {
+    let pattern = String::deserialize(d)?;
+    Regex::new(&pattern).map(Some).map_err(de::Error::custom)
+}
+
+/// Deserialize a record pattern string, checking that it compiles
+fn pattern<'de, D: Deserializer<'de>>(d: D) -> Result<String, D::Error> {
+    let pattern = String::deserialize(d)?;
+    Pattern::new(&pattern).map_err(de::Error::custom)?;
+    Ok(pattern)
+}
+
+/// Deserialize a timestamp format, checking that custom format descriptions are valid
+fn timestamp_format<'de, D: Deserializer<'de>>(d: D) -> Result<TimestampFormat, D::Error> {
+    let format = TimestampFormat::deserialize(d)?;
+    let config = TimestampConfig {
+        format,
+        ..Default::default()
+    };
+    Clock::new(Some(&config)).map_err(de::Error::custom)?;
+    Ok(config.format)
+}
+
+/// Deserialize a list of colors, checking that it has at least one color
+fn colors<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Rgb>, D::Error> {
+    let colors = Vec::<Rgb>::deserialize(d)?;
+
+    if colors.is_empty() {
+        return Err(de::Error::invalid_length(0, &"at least one color"));
+    }
+
+    Ok(colors)
+}
+
+/// Record format of a configuration file (the built-in `RecordFormat`s)
+#[derive(Clone, Debug, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordFormatDescription {
+    /// JSON format
+    Json,
+    /// simple log format
+    #[default]
+    Simple,
+    /// logfmt format
+    Logfmt,
+    /// format described by a pattern string (see `RecordFormat::Pattern`)
+    Pattern(#[serde(deserialize_with = "pattern")] String),
+    /// RFC 5424 syslog format
+    Rfc5424(SyslogConfig),
+    /// RFC 3164 syslog format
+    Rfc3164(SyslogConfig),
+}
+
+impl From<RecordFormatDescription> for RecordFormat {
+    fn from(description: RecordFormatDescription) -> RecordFormat {
+        match description {
+            RecordFormatDescription::Json => RecordFormat::Json,
+            RecordFormatDescription::Simple => RecordFormat::Simple,
+            RecordFormatDescription::Logfmt => RecordFormat::Logfmt,
+            RecordFormatDescription::Pattern(pattern) => RecordFormat::Pattern(pattern),
+            RecordFormatDescription::Rfc5424(config) => RecordFormat::Rfc5424(config),
+            RecordFormatDescription::Rfc3164(config) => RecordFormat::Rfc3164(config),
+        }
+    }
+}
+
+/// Color format of a configuration file (a `ColorFormat`, or `None` for no colors)
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorFormatDescription {
+    /// don't color records
+    None,
+    /// solid color per line
+    #[default]
+    Solid,
+    /// gradient over the characters of each line
+    InlineGradient(Gradient),
+    /// gradient over lines
+    MultiLineGradient(Gradient),
+    /// style each segment of a record independently
+    Segmented,
+    /// segmented, with the level drawn as a badge
+    Badged,
+    /// solid color per line, hashed from the record's target or thread name
+    Hashed(HashKey),
+}
+
+impl From<ColorFormatDescription> for Option<ColorFormat> {
+    fn from(description: ColorFormatDescription) -> Option<ColorFormat> {
+        match description {
+            ColorFormatDescription::None => None,
+            ColorFormatDescription::Solid => Some(ColorFormat::Solid),
+            ColorFormatDescription::InlineGradient(g) => Some(ColorFormat::InlineGradient(g)),
+            ColorFormatDescription::MultiLineGradient(g) => Some(ColorFormat::MultiLineGradient(g)),
+            ColorFormatDescription::Segmented => Some(ColorFormat::Segmented),
+            ColorFormatDescription::Badged => Some(ColorFormat::Badged),
+            ColorFormatDescription::Hashed(key) => Some(ColorFormat::Hashed(key)),
+        }
+    }
+}
+
+/// Colors of one level in a custom theme
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LevelColors {
+    /// colors that gradients move through, evenly spaced (one or more)
+    #[serde(deserialize_with = "colors")]
+    pub colors: Vec<Rgb>,
+    /// solid color of the level (`None` uses the last of `colors`)
+    #[serde(default)]
+    pub solid: Option<Rgb>,
+    /// colors that the `Hashed` color format picks from (empty picks colors along
+    /// `colors` instead)
+    #[serde(default)]
+    pub palette: Vec<Rgb>,
+}
+
+/// Theme with some levels' colors replaced
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CustomThemeDescription {
+    /// theme used for levels without colors of their own, and for text styles
+    pub base: ThemeDescription,
+    /// colors of `trace` records
+    pub trace: Option<LevelColors>,
+    /// colors of `debug` records
+    pub debug: Option<LevelColors>,
+    /// colors of `info` records
+    pub info: Option<LevelColors>,
+    /// colors of `warn` records
+    pub warn: Option<LevelColors>,
+    /// colors of `error` records
+    pub error: Option<LevelColors>,
+}
+
+impl Default for CustomThemeDescription {
+    /// Return a `CustomThemeDescription` that doesn't change its base theme
+    fn default() -> CustomThemeDescription {
+        CustomThemeDescription {
+            base: ThemeDescription::Spectral,
+            trace: None,
+            debug: None,
+            info: None,
+            warn: None,
+            error: None,
+        }
+    }
+}
+
+/// Theme of a configuration file, by name or with inline colors
+#[derive(Clone, Debug, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeDescription {
+    /// the `Spectral` theme
+    #[default]
+    Spectral,
+    /// the `Simple` theme
+    Simple,
+    /// a theme with colors given per level
+    Custom(Box<CustomThemeDescription>),
+}
+
+impl From<ThemeDescription> for Box<dyn Theme> {
+    fn from(description: ThemeDescription) -> Box<dyn Theme> {
+        match description {
+            ThemeDescription::Spectral => Box::new(theme::Spectral {}),
+            ThemeDescription::Simple => Box::new(theme::Simple {}),
+            ThemeDescription::Custom(custom) => Box::new(CustomTheme {
+                base: custom.base.into(),
+                levels: [
+                    custom.error,
+                    custom.warn,
+                    custom.info,
+                    custom.debug,
+                    custom.trace,
+                ],
+            }),
+        }
+    }
+}
+
+/// Theme made from a `CustomThemeDescription`
+struct CustomTheme {
+    /// theme used for levels without colors of their own
+    base: Box<dyn Theme>,
+    /// colors of each level, indexed from `Error` to `Trace`
+    levels: [Option<LevelColors>; 5],
+}
+
+impl CustomTheme {
+    /// Get the colors of a level, if they replace the base theme's
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - level to get colors of
+    fn colors(&self, level: Level) -> Option<&LevelColors> {
+        self.levels[level as usize - 1].as_ref()
+    }
+}
+
+impl Theme for CustomTheme {
+    fn solid(&self, level: Level) -> Rgb {
+        match self.colors(level) {
+            Some(c) => c.solid.unwrap_or(c.colors[c.colors.len() - 1]),
+            None => self.base.solid(level),
+        }
+    }
+
+    fn range(&self, level: Level) -> RgbRange {
+        match self.colors(level) {
+            Some(c) => RgbRange {
+                start: c.colors[0],
+                end: c.colors[c.colors.len() - 1],
+            },
+            None => self.base.range(level),
+        }
+    }
+
+    fn ramp(&self, level: Level) -> ColorRamp {
+        match self.colors(level) {
+            Some(c) => ColorRamp::even(&c.colors),
+            None => self.base.ramp(level),
+        }
+    }
+
+    fn palette(&self, level: Level) -> Vec<Rgb> {
+        match self.colors(level) {
+            Some(c) => c.palette.clone(),
+            None => self.base.palette(level),
+        }
+    }
+
+    fn level_style(&self, level: Level) -> Style {
+        self.base.level_style(level)
+    }
+
+    fn segment_style(&self, level: Level, segment: Segment) -> Style {
+        self.base.segment_style(level, segment)
+    }
+}
+
+/// Timestamps of a configuration file
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimestampDescription {
+    /// switch for adding timestamps to records
+    pub enabled: bool,
+    /// time zone offset of timestamps
+    pub offset: TimeOffset,
+    /// format of timestamps
+    #[serde(deserialize_with = "timestamp_format")]
+    pub format: TimestampFormat,
+}
+
+impl Default for TimestampDescription {
+    /// Return a `TimestampDescription` with default values
+    fn default() -> TimestampDescription {
+        let config = TimestampConfig::default();
+
+        TimestampDescription {
+            enabled: true,
+            offset: config.offset,
+            format: config.format,
+        }
+    }
+}
+
+impl From<TimestampDescription> for Option<TimestampConfig> {
+    fn from(description: TimestampDescription) -> Option<TimestampConfig> {
+        if description.enabled {
+            Some(TimestampConfig {
+                offset: description.offset,
+                format: description.format,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Output sink of a configuration file (see `SinkConfig`)
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SinkDescription {
+    /// log level filter for this sink
+    pub level: LevelFilter,
+    /// record formatting mode
+    pub record_format: RecordFormatDescription,
+    /// color formatting mode
+    pub color_format: ColorFormatDescription,
+    /// color theme
+    pub theme: ThemeDescription,
+    /// switch for enabling log splitting to `stderr`
+    pub use_stderr: bool,
+    /// output destination for log records
+    pub destination: Destination,
+    /// colors supported by console output (`None` detects them)
+    pub color_support: Option<ColorSupport>,
+    /// how colors are quantized for terminals without 24-bit color
+    pub quantization: Quantization,
+}
+
+impl Default for SinkDescription {
+    /// Return a `SinkDescription` with default values
+    fn default() -> SinkDescription {
+        SinkDescription {
+            level: LevelFilter::Trace,
+            record_format: RecordFormatDescription::default(),
+            color_format: ColorFormatDescription::default(),
+            theme: ThemeDescription::default(),
+            use_stderr: true,
+            destination: Destination::Console,
+            color_support: None,
+            quantization: Quantization::default(),
+        }
+    }
+}
+
+impl From<SinkDescription> for SinkConfig {
+    fn from(description: SinkDescription) -> SinkConfig {
+        SinkConfig {
+            level: description.level,
+            record_format: description.record_format.into(),
+            color_format: description.color_format.into(),
+            theme: description.theme.into(),
+            use_stderr: description.use_stderr,
+            destination: description.destination,
+            color_support: description.color_support,
+            quantization: description.quantization,
+        }
+    }
+}
+
+/// Logger configuration that can be loaded from a file (see `Config::from_file`)
+///
+/// Keys are the names of `Config`'s fields, and missing keys use their default values
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigDescription {
+    /// log level filter
+    pub level: LevelFilter,
+    /// per-target log level filters, by target prefix
+    pub directives: BTreeMap<String, LevelFilter>,
+    /// regular expression that log messages must match to be logged
+    #[serde(deserialize_with = "message_filter")]
+    pub message_filter: Option<Regex>,
+    /// record formatting mode
+    pub record_format: RecordFormatDescription,
+    /// color formatting mode
+    pub color_format: ColorFormatDescription,
+    /// color theme
+    pub theme: ThemeDescription,
+    /// switch for enabling log splitting to `stderr`
+    pub use_stderr: bool,
+    /// output destination for log records
+    pub destination: Destination,
+    /// colors supported by console output (`None` detects them)
+    pub color_support: Option<ColorSupport>,
+    /// how colors are quantized for terminals without 24-bit color
+    pub quantization: Quantization,
+    /// output sinks (empty writes to a single sink made from the settings above)
+    pub sinks: Vec<SinkDescription>,
+    /// settings for writing records on a background thread
+    pub async_writer: Option<AsyncConfig>,
+    /// key to nest structured key-values under in `Json` records
+    pub json_kv_key: Option<String>,
+    /// timestamps added by the built-in record formats
+    pub timestamp: TimestampDescription,
+    /// optional record fields included by the `Json` and `Simple` record formats
+    pub record_fields: RecordFields,
+}
+
+impl Default for ConfigDescription {
+    /// Return a `ConfigDescription` with the same values as `Config::default()`
+    fn default() -> ConfigDescription {
+        ConfigDescription {
+            level: LevelFilter::Info,
+            directives: BTreeMap::new(),
+            message_filter: None,
+            record_format: RecordFormatDescription::default(),
+            color_format: ColorFormatDescription::default(),
+            theme: ThemeDescription::default(),
+            use_stderr: true,
+            destination: Destination::Console,
+            color_support: None,
+            quantization: Quantization::default(),
+            sinks: vec![],
+            async_writer: None,
+            json_kv_key: None,
+            timestamp: TimestampDescription::default(),
+            record_fields: RecordFields::default(),
+        }
+    }
+}
+
+impl ConfigDescription {
+    /// Parse a configuration description
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - contents of the configuration file
+    /// * `format` - format of the configuration file
+    pub fn parse(s: &str, format: ConfigFormat) -> Result<ConfigDescription, ConfigError> {
+        // the key path of an error, or an empty path for errors outside of any key
+        let key = |path: &serde_path_to_error::Path| match path.to_string().as_str() {
+            "." => String::new(),
+            path => path.to_string(),
+        };
+
+        match format {
+            ConfigFormat::Toml => {
+                let de = toml::Deserializer::new(s);
+                serde_path_to_error::deserialize(de).map_err(|e| {
+                    let line = e
+                        .inner()
+                        .span()
+                        .map(|span| s[..span.start].matches('\n').count() + 1);
+
+                    ConfigError::Invalid {
+                        path: None,
+                        key: key(e.path()),
+                        line,
+                        reason: match e.inner().message().trim() {
+                            "" => "invalid TOML syntax".to_string(),
+                            msg => msg.to_string(),
+                        },
+                    }
+                })
+            }
+            ConfigFormat::Json => {
+                let mut de = serde_json::Deserializer::from_str(s);
+                let description: ConfigDescription = serde_path_to_error::deserialize(&mut de)
+                    .map_err(|e| ConfigError::Invalid {
+                        path: None,
+                        key: key(e.path()),
+                        line: Some(e.inner().line()),
+                        reason: strip_position(&e.inner().to_string()),
+                    })?;
+
+                de.end().map_err(|e| ConfigError::Invalid {
+                    path: None,
+                    key: String::new(),
+                    line: Some(e.line()),
+                    reason: strip_position(&e.to_string()),
+                })?;
+
+                Ok(description)
+            }
+        }
+    }
+}
+
+/// Remove the ` at line <n> column <m>` suffix that `serde_json` adds to error messages
+///
+/// # Arguments
+///
+/// * `msg` - error message
+fn strip_position(msg: &str) -> String {
+    match msg.rfind(" at line ") {
+        Some(i) => msg[..i].to_string(),
+        None => msg.to_string(),
+    }
+}
+
+impl From<ConfigDescription> for Config {
+    fn from(description: ConfigDescription) -> Config {
+        Config {
+            level: description.level,
+            directives: description
+                .directives
+                .into_iter()
+                .map(|(target, level)| Directive { target, level })
+                .collect(),
+            message_filter: description.message_filter,
+            record_format: description.record_format.into(),
+            color_format: description.color_format.into(),
+            theme: description.theme.into(),
+            use_stderr: description.use_stderr,
+            destination: description.destination,
+            color_support: description.color_support,
+            quantization: description.quantization,
+            sinks: description
+                .sinks
+                .into_iter()
+                .map(SinkConfig::from)
+                .collect(),
+            async_writer: description.async_writer,
+            json_kv_key: description.json_kv_key,
+            timestamp: description.timestamp.into(),
+            record_fields: description.record_fields,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempPath;
+    use crate::{ColorSpace, Traversal};
+
+    // helpers
+
+    /// Parse a configuration description, panicking if it is invalid
+    fn parse(s: &str, format: ConfigFormat) -> ConfigDescription {
+        ConfigDescription::parse(s, format).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Parse a configuration description, returning its error
+    fn parse_err(s: &str, format: ConfigFormat) -> ConfigError {
+        match ConfigDescription::parse(s, format) {
+            Ok(_) => panic!("config should be invalid"),
+            Err(e) => e,
+        }
+    }
+
+    // tests
+
+    #[test]
+    fn parse_empty_config_is_default() {
+        for (s, format) in [("", ConfigFormat::Toml), ("{}", ConfigFormat::Json)] {
+            let description = parse(s, format);
+
+            assert_eq!(description.level, LevelFilter::Info);
+            assert!(description.directives.is_empty());
+            assert_eq!(description.record_format, RecordFormatDescription::Simple);
+            assert_eq!(description.color_format, ColorFormatDescription::Solid);
+            assert_eq!(description.theme, ThemeDescription::Spectral);
+            assert!(description.use_stderr);
+            assert!(description.sinks.is_empty());
+            assert_eq!(description.timestamp, TimestampDescription::default());
+        }
+    }
+
+    #[test]
+    fn parse_reads_toml() {
+        let description = parse(
+            r##"
+            level = "debug"
+            message_filter = "^GET "
+            record_format = { pattern = "{level} {message}" }
+            color_format = { inline_gradient = { steps = 20, space = "oklab" } }
+            theme = "simple"
+            color_support = "ansi256"
+
+            [directives]
+            hyper = "off"
+            "my_app::db" = "trace"
+
+            [timestamp]
+            offset = "local"
+            format = { custom = "[hour]:[minute]" }
+
+            [[sinks]]
+            level = "warn"
+            record_format = "json"
+            destination = { file = { path = "/tmp/swing.log", max_bytes = 1024 } }
+
+            [[sinks]]
+            color_format = "badged"
+            "##,
+            ConfigFormat::Toml,
+        );
+
+        assert_eq!(description.level, LevelFilter::Debug);
+        assert_eq!(description.message_filter.unwrap().as_str(), "^GET ");
+        assert_eq!(
+            description.record_format,
+            RecordFormatDescription::Pattern("{level} {message}".to_string())
+        );
+        assert_eq!(
+            description.color_format,
+            ColorFormatDescription::InlineGradient(Gradient {
+                steps: 20,
+                space: ColorSpace::Oklab,
+                traversal: Traversal::default(),
+                easing: Default::default(),
+            })
+        );
+        assert_eq!(description.theme, ThemeDescription::Simple);
+        assert_eq!(description.color_support, Some(ColorSupport::Ansi256));
+        assert_eq!(description.directives["hyper"], LevelFilter::Off);
+        assert_eq!(description.directives["my_app::db"], LevelFilter::Trace);
+        assert_eq!(description.timestamp.offset, TimeOffset::Local);
+        assert_eq!(
+            description.timestamp.format,
+            TimestampFormat::Custom("[hour]:[minute]".to_string())
+        );
+
+        assert_eq!(description.sinks.len(), 2);
+        assert_eq!(description.sinks[0].level, LevelFilter::Warn);
+        assert_eq!(
+            description.sinks[0].record_format,
+            RecordFormatDescription::Json
+        );
+        match &description.sinks[0].destination {
+            Destination::File(config) => {
+                assert_eq!(config.path, PathBuf::from("/tmp/swing.log"));
+                assert_eq!(config.max_bytes, Some(1024));
+                assert_eq!(config.max_archives, 5);
+            }
+            _ => panic!("sink should write to a file"),
+        }
+        assert_eq!(description.sinks[1].level, LevelFilter::Trace);
+        assert_eq!(
+            description.sinks[1].color_format,
+            ColorFormatDescription::Badged
+        );
+    }
+
+    #[test]
+    fn parse_reads_json() {
+        let description = parse(
+            r##"{
+                "level": "warn",
+                "record_format": { "rfc5424": { "facility": "local0", "app_name": "app" } },
+                "color_format": { "hashed": "target" },
+                "theme": { "custom": { "error": { "colors": ["#800000", "red"] } } },
+                "timestamp": { "enabled": false },
+                "record_fields": { "thread": true }
+            }"##,
+            ConfigFormat::Json,
+        );
+
+        assert_eq!(description.level, LevelFilter::Warn);
+        match &description.record_format {
+            RecordFormatDescription::Rfc5424(config) => {
+                assert_eq!(config.facility, crate::Facility::Local0);
+                assert_eq!(config.app_name.as_deref(), Some("app"));
+            }
+            _ => panic!("record format should be rfc5424"),
+        }
+        assert_eq!(
+            description.color_format,
+            ColorFormatDescription::Hashed(HashKey::Target)
+        );
+        assert!(description.record_fields.thread);
+
+        let config: Config = description.into();
+        assert!(config.timestamp.is_none());
+        assert_eq!(config.theme.solid(Level::Error), crate::Color::Red.value());
+    }
+
+    #[test]
+    fn custom_theme_replaces_level_colors() {
+        let description = parse(
+            r##"
+            [theme.custom]
+            base = "simple"
+            warn = { colors = ["#102030", "#405060", "#708090"], palette = ["#ffffff"] }
+            info = { colors = ["#00ff00"], solid = "blue" }
+            "##,
+            ConfigFormat::Toml,
+        );
+        let theme: Box<dyn Theme> = description.theme.into();
+        let base = theme::Simple {};
+
+        assert_eq!(
+            theme.solid(Level::Warn),
+            Rgb {
+                r: 0x70,
+                g: 0x80,
+                b: 0x90
+            }
+        );
+        assert_eq!(
+            theme.range(Level::Warn),
+            RgbRange {
+                start: Rgb {
+                    r: 0x10,
+                    g: 0x20,
+                    b: 0x30
+                },
+                end: Rgb {
+                    r: 0x70,
+                    g: 0x80,
+                    b: 0x90
+                },
+            }
+        );
+        assert_eq!(
+            theme.palette(Level::Warn),
+            [Rgb {
+                r: 255,
+                g: 255,
+                b: 255
+            }]
+        );
+        assert_eq!(theme.solid(Level::Info), crate::Color::Blue.value());
+
+        // levels without colors of their own, and text styles, come from the base theme
+        assert_eq!(theme.solid(Level::Error), base.solid(Level::Error));
+        assert_eq!(theme.range(Level::Trace), base.range(Level::Trace));
+        assert_eq!(
+            theme.level_style(Level::Warn),
+            base.level_style(Level::Warn)
+        );
+    }
+
+    #[test]
+    fn parse_errors_name_the_invalid_key() {
+        let cases = [
+            ("level = \"loud\"", "level", Some(1)),
+            (
+                "[[sinks]]\n\n[[sinks]]\nlevel = \"loud\"",
+                "sinks[1].level",
+                Some(4),
+            ),
+            ("message_filter = \"(\"", "message_filter", Some(1)),
+            (
+                "record_format = { pattern = \"{nope}\" }",
+                "record_format.pattern",
+                Some(1),
+            ),
+            (
+                "[timestamp]\nformat = { custom = \"[nope]\" }",
+                "timestamp.format",
+                Some(2),
+            ),
+            (
+                "[theme.custom]\nerror = { colors = [\"#12345\"] }",
+                "theme.custom.error.colors[0]",
+                Some(2),
+            ),
+            (
+                "[theme.custom]\nerror = { colors = [] }",
+                "theme.custom.error.colors",
+                Some(2),
+            ),
+            ("colour_format = \"solid\"", "colour_format", Some(1)),
+        ];
+
+        for (s, key, line) in cases.iter() {
+            match parse_err(s, ConfigFormat::Toml) {
+                ConfigError::Invalid {
+                    key: k, line: l, ..
+                } => {
+                    assert_eq!(&k, key, "wrong key for {:?}", s);
+                    assert_eq!(&l, line, "wrong line for {:?}", s);
+                }
+                e => panic!("unexpected error {}", e),
+            }
+        }
+
+        let e = parse_err(
+            "{\n  \"sinks\": [{ \"theme\": \"neon\" }]\n}",
+            ConfigFormat::Json,
+        );
+        assert_eq!(
+            e.to_string(),
+            "invalid config at `sinks[0].theme` (line 2): unknown variant `neon`, expected one \
+             of `spectral`, `simple`, `custom`"
+        );
+    }
+
+    #[test]
+    fn parse_reports_syntax_errors() {
+        let e = parse_err("level = ", ConfigFormat::Toml);
+        assert!(matches!(e, ConfigError::Invalid { line: Some(1), .. }));
+
+        let e = parse_err("{\"level\": \"info\"} trailing", ConfigFormat::Json);
+        assert!(
+            matches!(&e, ConfigError::Invalid { key, line: Some(1), .. } if key.is_empty()),
+            "{}",
+            e
+        );
+    }
+
+    #[test]
+    fn from_file_chooses_format_by_extension() {
+        let toml_path = TempPath::new("config.toml");
+        let json_path = TempPath::new("config.json");
+        std::fs::write(&toml_path, "level = \"trace\"").unwrap();
+        std::fs::write(&json_path, "{\"level\": \"error\"}").unwrap();
+
+        assert_eq!(
+            Config::from_file(&toml_path).unwrap().level,
+            LevelFilter::Trace
+        );
+        assert_eq!(
+            Config::from_file(&json_path).unwrap().level,
+            LevelFilter::Error
+        );
+
+        // errors in a file name the file
+        std::fs::write(&json_path, "{\"level\": \"loud\"}").unwrap();
+        assert_eq!(
+            Config::from_file(&json_path).err().unwrap().to_string(),
+            format!(
+                "invalid config file {} at `level` (line 1): unknown variant `loud`, expected \
+                 one of `OFF`, `ERROR`, `WARN`, `INFO`, `DEBUG`, `TRACE`",
+                json_path.display()
+            )
+        );
+
+        assert!(matches!(
+            Config::from_file(TempPath::new("config-missing.toml")),
+            Err(ConfigError::Io { .. })
+        ));
+        assert!(matches!(
+            Config::from_file(toml_path.with_extension("yaml")),
+            Err(ConfigError::UnknownFormat(_))
+        ));
+    }
+}
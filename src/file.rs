@@ -0,0 +1,688 @@
+//! Log file output with size and time based rotation
+
+use serde::Deserialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use time::OffsetDateTime;
+
+/// Time interval after which a log file is rotated
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationInterval {
+    /// rotate when crossing an hour boundary (UTC)
+    Hourly,
+    /// rotate when crossing a day boundary (UTC)
+    Daily,
+}
+
+impl RotationInterval {
+    /// Length of this interval in seconds
+    fn seconds(&self) -> i64 {
+        match self {
+            RotationInterval::Hourly => 60 * 60,
+            RotationInterval::Daily => 60 * 60 * 24,
+        }
+    }
+}
+
+/// Naming scheme for archived (rotated) log files
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveNaming {
+    /// append an index to the file name, where `.1` is the most recent archive
+    /// (e.g. `app.log.1`, `app.log.2`, ...)
+    #[default]
+    Numbered,
+    /// append the UTC time of rotation to the file name
+    /// (e.g. `app.log.2022-07-31T20-25-31.108`)
+    Timestamped,
+}
+
+/// Default number of archived log files to keep
+fn default_max_archives() -> usize {
+    5
+}
+
+/// Configuration for logging to a file
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    /// path of the active log file
+    pub path: PathBuf,
+    /// rotate the log file before it grows larger than this many bytes (`None` disables
+    /// size based rotation)
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+    /// rotate the log file when crossing an interval boundary (`None` disables time based
+    /// rotation)
+    #[serde(default)]
+    pub interval: Option<RotationInterval>,
+    /// number of archived log files to keep (older archives are deleted)
+    #[serde(default = "default_max_archives")]
+    pub max_archives: usize,
+    /// naming scheme for archived log files
+    #[serde(default)]
+    pub naming: ArchiveNaming,
+}
+
+impl FileConfig {
+    /// Create a `FileConfig` for `path` without rotation
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path of the log file
+    pub fn new<P: Into<PathBuf>>(path: P) -> FileConfig {
+        FileConfig {
+            path: path.into(),
+            max_bytes: None,
+            interval: None,
+            max_archives: default_max_archives(),
+            naming: ArchiveNaming::default(),
+        }
+    }
+}
+
+/// Number of records dropped after the log file fails to open before opening it is
+/// retried (it is also retried when a new rotation interval starts)
+const OPEN_RETRY_RECORDS: u64 = 100;
+
+/// Currently open log file
+struct ActiveFile {
+    /// handle to the log file
+    file: File,
+    /// size of the log file in bytes
+    size: u64,
+    /// index of the rotation interval the log file was started in
+    period: i64,
+}
+
+/// Write logs to a file, rotating it based on size and time
+pub struct FileWriter {
+    /// configuration for the log file
+    config: FileConfig,
+    /// currently open log file (`None` if it has not been opened yet or failed to open)
+    active: Mutex<Option<ActiveFile>>,
+    /// whether the last attempt to open the log file failed (so repeated failures are
+    /// reported once)
+    failing: AtomicBool,
+    /// records dropped since the last failed attempt to open the log file
+    dropped: AtomicU64,
+    /// rotation interval of the last failed attempt to open the log file
+    failed_period: AtomicI64,
+}
+
+impl FileWriter {
+    /// Create a new FileWriter
+    ///
+    /// The log file is opened (or created) when the first record is written
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - configuration for the log file
+    pub fn new(config: FileConfig) -> FileWriter {
+        FileWriter {
+            config,
+            active: Mutex::new(None),
+            failing: AtomicBool::new(false),
+            dropped: AtomicU64::new(0),
+            failed_period: AtomicI64::new(0),
+        }
+    }
+
+    /// Write log message to the log file, rotating it first if necessary
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - the log message to write
+    pub fn write(&self, msg: &str) {
+        self.write_at(msg, OffsetDateTime::now_utc());
+    }
+
+    /// Flush the log file
+    pub fn flush(&self) {
+        if let Some(a) = self.active.lock().unwrap().as_mut() {
+            let _ = a.file.flush();
+        }
+    }
+
+    /// Write log message to the log file as if the current time were `now`
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - the log message to write
+    /// * `now` - the current time
+    fn write_at(&self, msg: &str, now: OffsetDateTime) {
+        let mut active = self.active.lock().unwrap();
+        let len = msg.len() as u64 + 1;
+
+        if active.is_none() && self.should_open(now) {
+            *active = self.open(now);
+        }
+
+        let needs_rotation = match active.as_ref() {
+            Some(a) => {
+                let size_exceeded =
+                    matches!(self.config.max_bytes, Some(max) if a.size > 0 && a.size + len > max);
+
+                size_exceeded || self.period(now) != a.period
+            }
+            None => false,
+        };
+
+        if needs_rotation {
+            match self.rotate(now) {
+                Ok(()) => *active = self.open(now),
+                Err(e) => {
+                    self.report("rotate", &e);
+
+                    // keep writing to the current file, and try again after another
+                    // `max_bytes` bytes or in the next interval
+                    if let Some(a) = active.as_mut() {
+                        a.size = 0;
+                        a.period = self.period(now);
+                    }
+                }
+            }
+        }
+
+        if let Some(a) = active.as_mut() {
+            if writeln!(a.file, "{}", msg).is_ok() {
+                a.size += len;
+            }
+        }
+    }
+
+    /// Get the index of the rotation interval that `time` falls in (0 without time based rotation)
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - the time to find the interval for
+    fn period(&self, time: OffsetDateTime) -> i64 {
+        match self.config.interval {
+            Some(interval) => time.unix_timestamp().div_euclid(interval.seconds()),
+            None => 0,
+        }
+    }
+
+    /// Check if the log file should be opened, backing off after it failed to open
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - the current time
+    fn should_open(&self, now: OffsetDateTime) -> bool {
+        !self.failing.load(Ordering::Relaxed)
+            || self.dropped.fetch_add(1, Ordering::Relaxed) + 1 >= OPEN_RETRY_RECORDS
+            || self.period(now) != self.failed_period.load(Ordering::Relaxed)
+    }
+
+    /// Open (or create) the log file for appending, reporting the first of a run of
+    /// failures to `stderr`
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - the current time
+    fn open(&self, now: OffsetDateTime) -> Option<ActiveFile> {
+        match self.try_open() {
+            Ok(a) => {
+                self.failing.store(false, Ordering::Relaxed);
+                Some(a)
+            }
+            Err(e) => {
+                if !self.failing.swap(true, Ordering::Relaxed) {
+                    self.report("open", &e);
+                }
+
+                self.dropped.store(0, Ordering::Relaxed);
+                self.failed_period
+                    .store(self.period(now), Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Report a failure to operate on the log file to `stderr`
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - what failed (e.g. `open`)
+    /// * `e` - the error that occurred
+    fn report(&self, action: &str, e: &io::Error) {
+        let _ = writeln!(
+            io::stderr(),
+            "swing: failed to {} log file {}: {}",
+            action,
+            self.config.path.display(),
+            e
+        );
+    }
+
+    /// Open (or create) the log file for appending
+    ///
+    /// The rotation interval of an existing log file is taken from its modification time, so a
+    /// file left over from an earlier interval is rotated before anything is written to it
+    fn try_open(&self) -> io::Result<ActiveFile> {
+        if let Some(parent) = self.config.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.config.path)?;
+        let metadata = file.metadata()?;
+        let modified = metadata.modified().unwrap_or_else(|_| SystemTime::now());
+
+        Ok(ActiveFile {
+            file,
+            size: metadata.len(),
+            period: self.period(OffsetDateTime::from(modified)),
+        })
+    }
+
+    /// Move the active log file to an archive and delete archives beyond the configured limit
+    ///
+    /// An error is returned if the active log file couldn't be moved, in which case it is
+    /// left in place (failures to delete old archives are only reported to `stderr`)
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - the time of rotation
+    fn rotate(&self, now: OffsetDateTime) -> io::Result<()> {
+        let path = &self.config.path;
+
+        if self.config.max_archives == 0 {
+            return fs::remove_file(path);
+        }
+
+        match self.config.naming {
+            ArchiveNaming::Numbered => {
+                for i in (1..self.config.max_archives).rev() {
+                    let from = archive_path(path, &i.to_string());
+
+                    if from.exists() {
+                        fs::rename(from, archive_path(path, &(i + 1).to_string()))?;
+                    }
+                }
+
+                fs::rename(path, archive_path(path, "1"))
+            }
+            ArchiveNaming::Timestamped => {
+                let stamp = format!(
+                    "{:04}-{:02}-{:02}T{:02}-{:02}-{:02}.{:03}",
+                    now.year(),
+                    u8::from(now.month()),
+                    now.day(),
+                    now.hour(),
+                    now.minute(),
+                    now.second(),
+                    now.millisecond()
+                );
+                let mut archive = archive_path(path, &stamp);
+                let mut n = 1;
+
+                // avoid overwriting an archive when rotating more than once per millisecond
+                while archive.exists() {
+                    archive = archive_path(path, &format!("{}.{}", stamp, n));
+                    n += 1;
+                }
+
+                fs::rename(path, archive)?;
+
+                if let Err(e) = self.prune_timestamped() {
+                    self.report("delete old archives of", &e);
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Delete the oldest timestamped archives so that at most `max_archives` remain
+    fn prune_timestamped(&self) -> io::Result<()> {
+        let path = &self.config.path;
+        let dir = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let prefix = match path.file_name() {
+            Some(name) => format!("{}.", name.to_string_lossy()),
+            None => return Ok(()),
+        };
+
+        let mut archives = fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.strip_prefix(&prefix).is_some_and(is_archive_timestamp))
+            .collect::<Vec<String>>();
+
+        // timestamps sort chronologically, so the oldest archives come first
+        archives.sort();
+
+        let excess = archives.len().saturating_sub(self.config.max_archives);
+
+        for name in archives.into_iter().take(excess) {
+            fs::remove_file(dir.join(name))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Check if an archive suffix is a rotation timestamp written by `FileWriter::rotate`
+/// (e.g. `2022-07-31T20-25-31.108`), optionally followed by a `.<n>` collision counter
+///
+/// # Arguments
+///
+/// * `suffix` - suffix of the archive's file name, after the log file's name and `.`
+fn is_archive_timestamp(suffix: &str) -> bool {
+    const LAYOUT: &[u8] = b"0000-00-00T00-00-00.000";
+
+    let bytes = suffix.as_bytes();
+
+    if bytes.len() < LAYOUT.len() {
+        return false;
+    }
+
+    let (stamp, counter) = bytes.split_at(LAYOUT.len());
+    let stamp_matches = stamp.iter().zip(LAYOUT).all(|(&b, &l)| match l {
+        b'0' => b.is_ascii_digit(),
+        _ => b == l,
+    });
+    let counter_matches = match counter.split_first() {
+        None => true,
+        Some((&b'.', n)) => !n.is_empty() && n.iter().all(u8::is_ascii_digit),
+        Some(_) => false,
+    };
+
+    stamp_matches && counter_matches
+}
+
+/// Get the path of an archive of the log file at `path`
+///
+/// # Arguments
+///
+/// * `path` - path of the active log file
+/// * `suffix` - suffix identifying the archive
+fn archive_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempPath;
+    use time::Duration;
+
+    // helpers
+
+    /// Get the sorted names of all files in `dir`
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - directory to list
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<String>>();
+        names.sort();
+        names
+    }
+
+    // tests
+
+    #[test]
+    fn write_appends_lines() {
+        let dir = TempPath::dir("write-appends");
+        let path = dir.join("app.log");
+        let writer = FileWriter::new(FileConfig::new(&path));
+
+        writer.write("foo");
+        writer.write("");
+        writer.write("bar");
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "foo\n\nbar\n");
+    }
+
+    #[test]
+    fn write_creates_missing_dirs() {
+        let dir = TempPath::dir("write-creates-dirs");
+        let path = dir.join("a").join("b").join("app.log");
+        let writer = FileWriter::new(FileConfig::new(&path));
+
+        writer.write("foo");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "foo\n");
+    }
+
+    #[test]
+    fn write_rotates_by_size_with_numbered_archives() {
+        let dir = TempPath::dir("rotate-size");
+        let path = dir.join("app.log");
+        let writer = FileWriter::new(FileConfig {
+            max_bytes: Some(8),
+            max_archives: 2,
+            ..FileConfig::new(&path)
+        });
+
+        // each line is 4 bytes, so every 2 lines fill the file
+        for line in ["aaa", "bbb", "ccc", "ddd", "eee", "fff", "ggg"] {
+            writer.write(line);
+        }
+
+        assert_eq!(file_names(&dir), vec!["app.log", "app.log.1", "app.log.2"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "ggg\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("app.log.1")).unwrap(),
+            "eee\nfff\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("app.log.2")).unwrap(),
+            "ccc\nddd\n"
+        );
+    }
+
+    #[test]
+    fn write_never_rotates_empty_file() {
+        let dir = TempPath::dir("rotate-oversized");
+        let path = dir.join("app.log");
+        let writer = FileWriter::new(FileConfig {
+            max_bytes: Some(2),
+            ..FileConfig::new(&path)
+        });
+
+        // lines larger than the limit are still written, one per file
+        writer.write("foo");
+        writer.write("bar");
+
+        assert_eq!(file_names(&dir), vec!["app.log", "app.log.1"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "bar\n");
+    }
+
+    #[test]
+    fn write_rotates_by_interval_with_timestamped_archives() {
+        let dir = TempPath::dir("rotate-interval");
+        let path = dir.join("app.log");
+        let writer = FileWriter::new(FileConfig {
+            interval: Some(RotationInterval::Hourly),
+            max_archives: 2,
+            naming: ArchiveNaming::Timestamped,
+            ..FileConfig::new(&path)
+        });
+        let start = OffsetDateTime::now_utc();
+
+        // the file is created now, so the first write at `start` doesn't rotate
+        writer.write_at("0", start);
+        writer.write_at("0", start);
+
+        for hour in 1..=3 {
+            writer.write_at(&hour.to_string(), start + Duration::hours(hour));
+        }
+
+        let names = file_names(&dir);
+        assert_eq!(names.len(), 3);
+        assert_eq!(names[0], "app.log");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "3\n");
+
+        // only the two newest archives are kept
+        assert_eq!(fs::read_to_string(dir.join(&names[1])).unwrap(), "1\n");
+        assert_eq!(fs::read_to_string(dir.join(&names[2])).unwrap(), "2\n");
+    }
+
+    #[test]
+    fn write_rotates_file_from_earlier_interval() {
+        let dir = TempPath::dir("rotate-stale");
+        let path = dir.join("app.log");
+        fs::write(&path, "old\n").unwrap();
+
+        let writer = FileWriter::new(FileConfig {
+            interval: Some(RotationInterval::Daily),
+            ..FileConfig::new(&path)
+        });
+
+        // the existing file was modified today, so it's rotated when writing tomorrow
+        writer.write_at("new", OffsetDateTime::now_utc() + Duration::days(1));
+
+        assert_eq!(file_names(&dir), vec!["app.log", "app.log.1"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+        assert_eq!(fs::read_to_string(dir.join("app.log.1")).unwrap(), "old\n");
+    }
+
+    #[test]
+    fn write_without_archives_truncates() {
+        let dir = TempPath::dir("rotate-no-archives");
+        let path = dir.join("app.log");
+        let writer = FileWriter::new(FileConfig {
+            max_bytes: Some(4),
+            max_archives: 0,
+            ..FileConfig::new(&path)
+        });
+
+        writer.write("foo");
+        writer.write("bar");
+
+        assert_eq!(file_names(&dir), vec!["app.log"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "bar\n");
+    }
+
+    #[test]
+    fn write_keeps_current_file_when_rotation_fails() {
+        let dir = TempPath::dir("rotate-fails");
+        let path = dir.join("app.log");
+
+        // a non-empty directory in the way of the first archive makes renaming fail
+        fs::create_dir_all(dir.join("app.log.1")).unwrap();
+        fs::write(dir.join("app.log.1").join("keep"), "").unwrap();
+
+        let writer = FileWriter::new(FileConfig {
+            max_bytes: Some(4),
+            max_archives: 1,
+            ..FileConfig::new(&path)
+        });
+
+        writer.write("foo");
+        writer.write("bar");
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "foo\nbar\n");
+        assert_eq!(file_names(&dir.join("app.log.1")), vec!["keep"]);
+    }
+
+    #[test]
+    fn prune_timestamped_ignores_other_archives() {
+        let dir = TempPath::dir("prune-other");
+        let path = dir.join("app.log");
+        let names = [
+            "app.log.1",
+            "app.log.2",
+            "app.log.2022-07-31",
+            "app.log.2022-07-31T20-25-31.108",
+            "app.log.2022-07-31T20-25-31.108.1",
+            "app.log.2022-08-01T00-00-00.000",
+        ];
+
+        for name in names {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let writer = FileWriter::new(FileConfig {
+            max_archives: 1,
+            naming: ArchiveNaming::Timestamped,
+            ..FileConfig::new(&path)
+        });
+        writer.prune_timestamped().unwrap();
+
+        // only the oldest timestamped archives are deleted
+        assert_eq!(
+            file_names(&dir),
+            vec![
+                "app.log.1",
+                "app.log.2",
+                "app.log.2022-07-31",
+                "app.log.2022-08-01T00-00-00.000",
+            ]
+        );
+    }
+
+    #[test]
+    fn write_backs_off_after_open_fails() {
+        let dir = TempPath::dir("open-fails");
+        let path = dir.join("blocker").join("app.log");
+
+        // a file in place of the log file's directory makes opening fail
+        fs::write(dir.join("blocker"), "").unwrap();
+        let writer = FileWriter::new(FileConfig::new(&path));
+
+        writer.write("a");
+        assert!(writer.failing.load(Ordering::Relaxed));
+
+        // opening is retried (and fails again) after `OPEN_RETRY_RECORDS` dropped records,
+        // but only the first failure of the run is reported (`failing` was already set)
+        for _ in 0..OPEN_RETRY_RECORDS {
+            writer.write("a");
+        }
+        assert_eq!(writer.dropped.load(Ordering::Relaxed), 0);
+        assert!(writer.failing.load(Ordering::Relaxed));
+
+        // opening isn't retried for every record, even once it would succeed
+        fs::remove_file(dir.join("blocker")).unwrap();
+
+        for _ in 1..OPEN_RETRY_RECORDS {
+            writer.write("b");
+        }
+        assert!(!path.exists());
+        assert!(writer.failing.load(Ordering::Relaxed));
+
+        writer.write("c");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "c\n");
+        assert!(!writer.failing.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn write_retries_open_in_next_interval() {
+        let dir = TempPath::dir("open-retry-interval");
+        let path = dir.join("blocker").join("app.log");
+        fs::write(dir.join("blocker"), "").unwrap();
+
+        let writer = FileWriter::new(FileConfig {
+            interval: Some(RotationInterval::Hourly),
+            ..FileConfig::new(&path)
+        });
+        let start = OffsetDateTime::now_utc();
+
+        writer.write_at("a", start);
+        fs::remove_file(dir.join("blocker")).unwrap();
+        writer.write_at("b", start);
+        assert!(!path.exists());
+
+        writer.write_at("c", start + Duration::hours(1));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "c\n");
+    }
+}
@@ -0,0 +1,435 @@
+//! Level filtering related definitions and implementation
+
+use log::{LevelFilter, Metadata};
+use regex::Regex;
+use std::fmt;
+use std::str::FromStr;
+
+/// Level filter that applies to all records whose target starts with some prefix
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Directive {
+    /// target prefix this directive applies to (e.g. `my_crate::db`)
+    ///
+    /// A prefix matches a target if it is equal to the target or if it is one of the
+    /// target's parent modules (`my_crate` matches `my_crate::db`, but not `my_crate_utils`)
+    pub target: String,
+    /// log level filter for records with a matching target
+    pub level: LevelFilter,
+}
+
+/// Check if the target prefix `prefix` applies to the target `target`
+///
+/// # Arguments
+///
+/// * `prefix` - target prefix from a directive
+/// * `target` - target of a log record
+fn prefix_matches(prefix: &str, target: &str) -> bool {
+    match target.strip_prefix(prefix) {
+        Some(rest) => prefix.is_empty() || rest.is_empty() || rest.starts_with("::"),
+        None => false,
+    }
+}
+
+/// Filter log records by level, with per-target overrides
+pub struct TargetFilter {
+    /// level filter used for targets without a matching directive
+    default: LevelFilter,
+    /// per-target level filters
+    directives: Vec<Directive>,
+}
+
+impl TargetFilter {
+    /// Create a new TargetFilter
+    ///
+    /// # Arguments
+    ///
+    /// * `default` - level filter used for targets without a matching directive
+    /// * `directives` - per-target level filters
+    pub fn new(default: LevelFilter, directives: Vec<Directive>) -> TargetFilter {
+        TargetFilter {
+            default,
+            directives,
+        }
+    }
+
+    /// Set the level filter used for targets without a matching directive
+    ///
+    /// # Arguments
+    ///
+    /// * `default` - new default level filter
+    pub fn set_default(&mut self, default: LevelFilter) {
+        self.default = default;
+    }
+
+    /// Replace the per-target level filters
+    ///
+    /// # Arguments
+    ///
+    /// * `directives` - new per-target level filters
+    pub fn set_directives(&mut self, directives: Vec<Directive>) {
+        self.directives = directives;
+    }
+
+    /// Get the level filter that applies to `target`
+    ///
+    /// The directive with the longest matching target prefix is used, falling
+    /// back to the default level filter when no directive matches.  If several
+    /// directives have the same target, the last one wins.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - target of a log record
+    pub fn level(&self, target: &str) -> LevelFilter {
+        self.directives
+            .iter()
+            .filter(|d| prefix_matches(&d.target, target))
+            .max_by_key(|d| d.target.len())
+            .map_or(self.default, |d| d.level)
+    }
+
+    /// Check if a record with this metadata passes the filter
+    ///
+    /// # Arguments
+    ///
+    /// * `metadata` - metadata of the record to check
+    pub fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level(metadata.target())
+    }
+
+    /// Get the most verbose level filter of the default and all directives
+    pub fn max_level(&self) -> LevelFilter {
+        self.directives
+            .iter()
+            .map(|d| d.level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+/// Error returned when an `env_logger` style filter string can't be parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FilterError {
+    /// a directive has an empty target (e.g. `=info`)
+    EmptyTarget(String),
+    /// a directive has a level that isn't one of `off`, `error`, `warn`, `info`, `debug` or `trace`
+    InvalidLevel {
+        /// the directive containing the invalid level
+        directive: String,
+        /// the invalid level
+        level: String,
+    },
+    /// a directive contains more than one `=`
+    InvalidDirective(String),
+    /// the message filter after `/` is not a valid regular expression
+    InvalidRegex {
+        /// the invalid message filter
+        pattern: String,
+        /// description of why the regex is invalid
+        reason: String,
+    },
+    /// the environment variable holding the filter string is not valid unicode
+    NotUnicode(String),
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FilterError::EmptyTarget(directive) => {
+                write!(f, "empty target in filter directive \"{}\"", directive)
+            }
+            FilterError::InvalidLevel { directive, level } => write!(
+                f,
+                "invalid log level \"{}\" in filter directive \"{}\" (expected one of off, error, warn, info, debug, trace)",
+                level, directive
+            ),
+            FilterError::InvalidDirective(directive) => write!(
+                f,
+                "invalid filter directive \"{}\" (expected <level>, <target> or <target>=<level>)",
+                directive
+            ),
+            FilterError::InvalidRegex { pattern, reason } => {
+                write!(f, "invalid message filter \"{}\": {}", pattern, reason)
+            }
+            FilterError::NotUnicode(var) => {
+                write!(f, "environment variable {} is not valid unicode", var)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+/// Level, target and message filters parsed from an `env_logger` style filter string
+///
+/// Filter strings are comma separated lists of directives, optionally followed by
+/// `/` and a regular expression that log messages must match, e.g.
+/// `info,my_crate::net=trace,hyper=off/timeout`.  Each directive is one of:
+///
+/// - `<level>`: set the default level filter
+/// - `<target>`: enable all levels for `target`
+/// - `<target>=<level>`: set the level filter for `target`
+#[derive(Clone, Debug)]
+pub struct FilterSpec {
+    /// default level filter, if one was given
+    pub level: Option<LevelFilter>,
+    /// per-target level filters
+    pub directives: Vec<Directive>,
+    /// regular expression that log messages must match, if one was given
+    pub message_filter: Option<Regex>,
+}
+
+impl FilterSpec {
+    /// Get the default level filter for this spec
+    ///
+    /// If no default level was given, `fallback` is used when the spec has no
+    /// directives, and `LevelFilter::Off` is used otherwise, so that only the
+    /// listed targets are logged (matching `env_logger`'s behavior)
+    ///
+    /// # Arguments
+    ///
+    /// * `fallback` - level filter to use for an empty spec
+    pub fn default_level(&self, fallback: LevelFilter) -> LevelFilter {
+        match self.level {
+            Some(level) => level,
+            None if self.directives.is_empty() => fallback,
+            None => LevelFilter::Off,
+        }
+    }
+}
+
+impl FromStr for FilterSpec {
+    type Err = FilterError;
+
+    /// Parse an `env_logger` style filter string
+    ///
+    /// # Arguments
+    ///
+    /// * `spec` - the filter string to parse
+    fn from_str(spec: &str) -> Result<FilterSpec, FilterError> {
+        let (dirs, pattern) = match spec.split_once('/') {
+            Some((dirs, pattern)) => (dirs, Some(pattern)),
+            None => (spec, None),
+        };
+
+        let mut level = None;
+        let mut directives = vec![];
+
+        for dir in dirs.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let parts = dir.split('=').map(str::trim).collect::<Vec<&str>>();
+
+            match parts.as_slice() {
+                [part] => match part.parse::<LevelFilter>() {
+                    Ok(l) => level = Some(l),
+                    Err(_) => directives.push(Directive {
+                        target: part.to_string(),
+                        level: LevelFilter::Trace,
+                    }),
+                },
+                [target, lvl] => {
+                    if target.is_empty() {
+                        return Err(FilterError::EmptyTarget(dir.to_string()));
+                    }
+
+                    let lvl = lvl.parse().map_err(|_| FilterError::InvalidLevel {
+                        directive: dir.to_string(),
+                        level: lvl.to_string(),
+                    })?;
+
+                    directives.push(Directive {
+                        target: target.to_string(),
+                        level: lvl,
+                    });
+                }
+                _ => return Err(FilterError::InvalidDirective(dir.to_string())),
+            }
+        }
+
+        let message_filter = match pattern {
+            Some(p) => Some(Regex::new(p).map_err(|e| FilterError::InvalidRegex {
+                pattern: p.to_string(),
+                reason: e.to_string(),
+            })?),
+            None => None,
+        };
+
+        Ok(FilterSpec {
+            level,
+            directives,
+            message_filter,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+
+    /// Create a directive for `target` at `level`
+    fn directive(target: &str, level: LevelFilter) -> Directive {
+        Directive {
+            target: target.to_string(),
+            level,
+        }
+    }
+
+    #[test]
+    fn level_uses_longest_matching_prefix() {
+        let filter = TargetFilter::new(
+            LevelFilter::Info,
+            vec![
+                directive("our_crate", LevelFilter::Warn),
+                directive("our_crate::db", LevelFilter::Debug),
+                directive("hyper", LevelFilter::Off),
+            ],
+        );
+
+        assert_eq!(filter.level("main"), LevelFilter::Info);
+        assert_eq!(filter.level("our_crate"), LevelFilter::Warn);
+        assert_eq!(filter.level("our_crate::net"), LevelFilter::Warn);
+        assert_eq!(filter.level("our_crate::db"), LevelFilter::Debug);
+        assert_eq!(filter.level("our_crate::db::pool"), LevelFilter::Debug);
+        assert_eq!(filter.level("hyper::client"), LevelFilter::Off);
+    }
+
+    #[test]
+    fn level_only_matches_whole_path_segments() {
+        let filter = TargetFilter::new(
+            LevelFilter::Info,
+            vec![directive("our_crate", LevelFilter::Trace)],
+        );
+
+        assert_eq!(filter.level("our_crate_utils"), LevelFilter::Info);
+        assert_eq!(filter.level("our_crate::utils"), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn level_prefers_last_duplicate_directive() {
+        let filter = TargetFilter::new(
+            LevelFilter::Info,
+            vec![
+                directive("hyper", LevelFilter::Trace),
+                directive("hyper", LevelFilter::Error),
+            ],
+        );
+
+        assert_eq!(filter.level("hyper"), LevelFilter::Error);
+    }
+
+    #[test]
+    fn enabled_checks_level_for_target() {
+        let filter = TargetFilter::new(
+            LevelFilter::Warn,
+            vec![directive("our_crate::db", LevelFilter::Debug)],
+        );
+        let mut mb = Metadata::builder();
+
+        assert!(filter.enabled(&mb.level(Level::Debug).target("our_crate::db").build()));
+        assert!(!filter.enabled(&mb.level(Level::Trace).target("our_crate::db").build()));
+        assert!(!filter.enabled(&mb.level(Level::Debug).target("hyper").build()));
+        assert!(filter.enabled(&mb.level(Level::Warn).target("hyper").build()));
+    }
+
+    #[test]
+    fn max_level_is_most_verbose_directive() {
+        let filter = TargetFilter::new(LevelFilter::Warn, vec![]);
+        assert_eq!(filter.max_level(), LevelFilter::Warn);
+
+        let filter = TargetFilter::new(
+            LevelFilter::Warn,
+            vec![
+                directive("a", LevelFilter::Debug),
+                directive("b", LevelFilter::Off),
+            ],
+        );
+        assert_eq!(filter.max_level(), LevelFilter::Debug);
+
+        let filter =
+            TargetFilter::new(LevelFilter::Trace, vec![directive("a", LevelFilter::Error)]);
+        assert_eq!(filter.max_level(), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn filter_spec_parses_env_logger_syntax() {
+        let spec: FilterSpec = "info,my_crate::net=trace,hyper=off,tokio".parse().unwrap();
+
+        assert_eq!(spec.level, Some(LevelFilter::Info));
+        assert_eq!(
+            spec.directives,
+            vec![
+                directive("my_crate::net", LevelFilter::Trace),
+                directive("hyper", LevelFilter::Off),
+                directive("tokio", LevelFilter::Trace),
+            ]
+        );
+        assert!(spec.message_filter.is_none());
+    }
+
+    #[test]
+    fn filter_spec_parses_message_filter() {
+        let spec: FilterSpec = "warn/time(out)?$".parse().unwrap();
+        let message_filter = spec.message_filter.unwrap();
+
+        assert_eq!(spec.level, Some(LevelFilter::Warn));
+        assert!(message_filter.is_match("request timeout"));
+        assert!(!message_filter.is_match("timeouts"));
+
+        let spec: FilterSpec = "/foo".parse().unwrap();
+        assert_eq!(spec.level, None);
+        assert!(spec.message_filter.is_some());
+    }
+
+    #[test]
+    fn filter_spec_ignores_whitespace_and_empty_directives() {
+        let spec: FilterSpec = " debug , ,hyper = warn,".parse().unwrap();
+
+        assert_eq!(spec.level, Some(LevelFilter::Debug));
+        assert_eq!(spec.directives, vec![directive("hyper", LevelFilter::Warn)]);
+
+        let spec: FilterSpec = "".parse().unwrap();
+        assert_eq!(spec.level, None);
+        assert!(spec.directives.is_empty());
+    }
+
+    #[test]
+    fn filter_spec_default_level_matches_env_logger() {
+        let spec: FilterSpec = "".parse().unwrap();
+        assert_eq!(spec.default_level(LevelFilter::Info), LevelFilter::Info);
+
+        let spec: FilterSpec = "hyper=warn".parse().unwrap();
+        assert_eq!(spec.default_level(LevelFilter::Info), LevelFilter::Off);
+
+        let spec: FilterSpec = "hyper=warn,error".parse().unwrap();
+        assert_eq!(spec.default_level(LevelFilter::Info), LevelFilter::Error);
+    }
+
+    #[test]
+    fn filter_spec_reports_malformed_directives() {
+        let test_cases = vec![
+            (
+                "info,hyper=verbose",
+                FilterError::InvalidLevel {
+                    directive: "hyper=verbose".to_string(),
+                    level: "verbose".to_string(),
+                },
+            ),
+            (
+                "hyper=",
+                FilterError::InvalidLevel {
+                    directive: "hyper=".to_string(),
+                    level: "".to_string(),
+                },
+            ),
+            ("=info", FilterError::EmptyTarget("=info".to_string())),
+            ("a=b=c", FilterError::InvalidDirective("a=b=c".to_string())),
+        ];
+
+        for (spec, expected) in test_cases {
+            assert_eq!(spec.parse::<FilterSpec>().unwrap_err(), expected);
+        }
+
+        match "info/(".parse::<FilterSpec>().unwrap_err() {
+            FilterError::InvalidRegex { pattern, .. } => assert_eq!(pattern, "("),
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+}
@@ -0,0 +1,477 @@
+//! Runtime reconfiguration of a `Logger`
+
+use crate::sink::LogSink;
+use crate::watch::ConfigWatcher;
+use crate::{
+    ColorFormat, Config, Directive, FormatError, LoggerState, RecordFormat, SinkConfig, Theme,
+};
+use log::{LevelFilter, Record};
+use regex::Regex;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, RwLock, RwLockWriteGuard};
+use std::time::Duration;
+use std::{mem, panic};
+
+/// Cloneable handle used to reconfigure a `Logger` at runtime (e.g. from an
+/// admin endpoint or a signal handler)
+///
+/// Each change is applied atomically: records being logged when a change is made
+/// are finished using the old settings, and records logged afterwards use the new
+/// settings.  Format, color, and theme changes apply to the logger's first sink (its only
+/// sink, unless `Config::sinks` was used), while other sinks can be replaced with `set_sink`.
+/// Changes to level filters of the installed logger (see `Logger::init`) also update
+/// the global maximum log level (`log::set_max_level`).
+#[derive(Clone)]
+pub struct LoggerHandle {
+    /// state shared with the logger
+    state: Arc<RwLock<LoggerState>>,
+}
+
+impl LoggerHandle {
+    /// Create a new LoggerHandle
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - state shared with the logger
+    pub(crate) fn new(state: Arc<RwLock<LoggerState>>) -> LoggerHandle {
+        LoggerHandle { state }
+    }
+
+    /// Lock the shared state for writing, waiting for in-flight records to be logged
+    fn lock(&self) -> RwLockWriteGuard<'_, LoggerState> {
+        self.state.write().unwrap()
+    }
+
+    /// Replace the default log level filter (used for targets without a matching directive)
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - new log level filter
+    pub fn set_level(&self, level: LevelFilter) {
+        let mut state = self.lock();
+        state.target_filter.set_default(level);
+        state.update_max_level();
+    }
+
+    /// Replace the per-target log level filters
+    ///
+    /// # Arguments
+    ///
+    /// * `directives` - new per-target log level filters
+    pub fn set_directives(&self, directives: Vec<Directive>) {
+        let mut state = self.lock();
+        state.target_filter.set_directives(directives);
+        state.update_max_level();
+    }
+
+    /// Replace the regex that log messages must match to be logged
+    ///
+    /// # Arguments
+    ///
+    /// * `message_filter` - new message filter (`None` logs all messages)
+    pub fn set_message_filter(&self, message_filter: Option<Regex>) {
+        self.lock().message_filter = message_filter;
+    }
+
+    /// Replace the record format of the first sink
+    ///
+    /// If the record format is invalid, an error is returned and the current record
+    /// format is kept
+    ///
+    /// # Arguments
+    ///
+    /// * `record_format` - new record format
+    pub fn set_record_format(&self, record_format: RecordFormat) -> Result<(), FormatError> {
+        match self.lock().sinks.first_mut() {
+            Some(sink) => sink.log_sculptor.set_record_format(record_format),
+            None => Ok(()),
+        }
+    }
+
+    /// Replace the color format of the first sink
+    ///
+    /// # Arguments
+    ///
+    /// * `color_format` - new color format (`None` disables coloring)
+    pub fn set_color_format(&self, color_format: Option<ColorFormat>) {
+        if let Some(sink) = self.lock().sinks.first_mut() {
+            sink.set_color_format(color_format);
+        }
+    }
+
+    /// Replace the color theme of the first sink
+    ///
+    /// # Arguments
+    ///
+    /// * `theme` - new color theme
+    pub fn set_theme(&self, theme: Box<dyn Theme>) {
+        if let Some(sink) = self.lock().sinks.first_mut() {
+            sink.log_painter.set_theme(theme);
+        }
+    }
+
+    /// Wait for queued records to be written, then flush every sink's writer
+    pub fn flush(&self) {
+        self.state.read().unwrap().flush();
+    }
+
+    /// Get a guard that flushes the logger when it is dropped
+    ///
+    /// Keeping the guard alive until the end of `main` makes sure that records
+    /// queued by an async writer or buffered by a writer are not lost on exit
+    pub fn flush_guard(&self) -> FlushGuard {
+        FlushGuard {
+            handle: self.clone(),
+        }
+    }
+
+    /// Install a panic hook that flushes the logger before the panic is reported
+    /// and the stack unwinds (or the process aborts)
+    ///
+    /// The previously installed panic hook is still called after flushing
+    pub fn flush_on_panic(&self) {
+        let state = Arc::clone(&self.state);
+        let prev_hook = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            // don't block if the lock is unavailable, since the panic may have
+            // happened while the logger was being reconfigured
+            if let Ok(state) = state.try_read() {
+                state.flush();
+            }
+
+            prev_hook(info);
+        }));
+    }
+
+    /// Get the number of records dropped because the async write queue was full
+    pub fn dropped_records(&self) -> u64 {
+        self.state
+            .read()
+            .unwrap()
+            .queue
+            .as_ref()
+            .map_or(0, |q| q.dropped())
+    }
+
+    /// Replace the sink at `index` (in the order sinks were configured), or add a new
+    /// sink if `index` is the number of sinks
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - index of the sink to replace
+    /// * `config` - configuration for the new sink
+    ///
+    /// If the sink's record format is invalid, an error is returned and the sinks are
+    /// left unchanged
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the number of sinks
+    pub fn set_sink(&self, index: usize, config: SinkConfig) -> Result<(), FormatError> {
+        let mut state = self.lock();
+        let sink = LogSink::new(config, &state.sculpt_options)?;
+
+        if index == state.sinks.len() {
+            state.sinks.push(sink);
+        } else {
+            state.sinks[index] = sink;
+        }
+
+        state.update_max_level();
+        Ok(())
+    }
+
+    /// Replace the logger's whole configuration (filters, formats, themes, and sinks)
+    ///
+    /// Records already queued by an async writer are written with the old settings
+    /// before the new configuration is applied.  If a record or timestamp format in
+    /// `config` is invalid, an error is returned and the current configuration is kept
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - new configuration
+    pub fn set_config(&self, config: Config) -> Result<(), FormatError> {
+        let new_state = LoggerState::new(config)?;
+
+        let old_state = {
+            let mut state = self.lock();
+            state.flush();
+            new_state
+                .installed
+                .store(state.installed.load(Ordering::SeqCst), Ordering::SeqCst);
+            let old_state = mem::replace(&mut *state, new_state);
+            state.update_max_level();
+            old_state
+        };
+
+        // stop the old async writer (if any) after the lock is released
+        drop(old_state);
+        Ok(())
+    }
+
+    /// Watch a configuration file (see `Config::from_file`) on a background thread, and
+    /// apply its configuration with `set_config` whenever the file changes
+    ///
+    /// The file's modification time and size are checked every `interval`.  A message
+    /// with the target `swing` is logged after each reload, or when the changed file
+    /// can't be loaded (in which case the current configuration is kept).  The file
+    /// is watched until the returned `ConfigWatcher` is dropped
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path of a `.toml` or `.json` configuration file
+    /// * `interval` - time between checks for changes
+    pub fn watch_config_file<P: AsRef<Path>>(&self, path: P, interval: Duration) -> ConfigWatcher {
+        ConfigWatcher::new(self.clone(), path.as_ref().to_path_buf(), interval)
+    }
+
+    /// Log a record through the logger's filters and sinks
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - the record to log
+    pub(crate) fn log(&self, record: &Record) {
+        self.state.read().unwrap().log(record);
+    }
+}
+
+/// Guard that flushes a logger when it is dropped (see `LoggerHandle::flush_guard`)
+#[must_use = "the logger is flushed when the guard is dropped"]
+pub struct FlushGuard {
+    /// handle to the logger to flush
+    handle: LoggerHandle,
+}
+
+impl Drop for FlushGuard {
+    /// Flush the logger
+    fn drop(&mut self) {
+        self.handle.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempPath;
+    use crate::{theme, AsyncConfig, Config, Destination, FileConfig, Gradient, Logger};
+    use log::{Level, Log, Metadata, Record};
+    use std::{fs, thread};
+
+    #[test]
+    fn handle_changes_level_filters() {
+        let logger = Logger::new();
+        let handle = logger.handle();
+        let mut mb = Metadata::builder();
+
+        assert!(!logger.enabled(&mb.level(Level::Debug).target("db").build()));
+
+        handle.set_level(LevelFilter::Debug);
+        assert!(logger.enabled(&mb.level(Level::Debug).target("db").build()));
+
+        handle.set_directives(vec![Directive {
+            target: "db".to_string(),
+            level: LevelFilter::Error,
+        }]);
+        assert!(!logger.enabled(&mb.level(Level::Warn).target("db").build()));
+        assert!(logger.enabled(&mb.level(Level::Debug).target("net").build()));
+    }
+
+    #[test]
+    fn handle_to_uninstalled_logger_keeps_global_max_level() {
+        let logger = Logger::new();
+        let handle = logger.handle();
+        let max_level = log::max_level();
+
+        handle.set_level(LevelFilter::Trace);
+        handle.set_directives(vec![Directive {
+            target: "db".to_string(),
+            level: LevelFilter::Trace,
+        }]);
+        handle.set_sink(1, SinkConfig::default()).unwrap();
+        handle.set_config(Config::default()).unwrap();
+
+        // only the installed logger may change the global max level
+        assert_eq!(log::max_level(), max_level);
+    }
+
+    #[test]
+    fn handle_clones_share_logger() {
+        let logger = Logger::new();
+        let handle = logger.handle().clone();
+        let mut mb = Metadata::builder();
+
+        handle.set_level(LevelFilter::Off);
+        assert!(!logger.enabled(&mb.level(Level::Error).build()));
+    }
+
+    #[test]
+    fn handle_changes_formats_while_logging() {
+        let config = Config {
+            color_format: None,
+            use_stderr: false,
+            ..Default::default()
+        };
+        let logger = Arc::new(Logger::with_config(config));
+        let handle = logger.handle();
+
+        // swapping formats and themes while other threads log shouldn't panic or deadlock
+        let writer = {
+            let logger = Arc::clone(&logger);
+            thread::spawn(move || {
+                for _ in 0..10 {
+                    logger.log(
+                        &Record::builder()
+                            .args(format_args!("foo"))
+                            .level(Level::Info)
+                            .target("test")
+                            .build(),
+                    );
+                }
+            })
+        };
+
+        for i in 0..100 {
+            if i % 2 == 0 {
+                handle.set_record_format(RecordFormat::Json).unwrap();
+                handle.set_color_format(Some(ColorFormat::InlineGradient(Gradient::new(10))));
+                handle.set_theme(Box::new(theme::Simple {}));
+            } else {
+                handle.set_record_format(RecordFormat::Simple).unwrap();
+                handle.set_color_format(None);
+                handle.set_theme(Box::new(theme::Spectral {}));
+            }
+        }
+
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn handle_changes_record_format() {
+        let logger = Logger::new();
+        let handle = logger.handle();
+        let rec = Record::builder()
+            .args(format_args!("foo"))
+            .level(Level::Info)
+            .target("test")
+            .build();
+
+        handle
+            .set_record_format(RecordFormat::Custom(Box::new(|r| r.args().to_string())))
+            .unwrap();
+        let msg = logger.state.read().unwrap().sinks[0]
+            .log_sculptor
+            .sculpt(&rec)
+            .into_text();
+        assert_eq!(msg, "foo");
+    }
+
+    #[test]
+    fn handle_replaces_and_adds_sinks() {
+        let logger = Logger::with_config(Config {
+            level: LevelFilter::Trace,
+            ..Default::default()
+        });
+        let handle = logger.handle();
+        let mut mb = Metadata::builder();
+
+        handle
+            .set_sink(
+                0,
+                SinkConfig {
+                    level: LevelFilter::Warn,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(!logger.enabled(&mb.level(Level::Info).build()));
+
+        handle
+            .set_sink(
+                1,
+                SinkConfig {
+                    level: LevelFilter::Debug,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(logger.enabled(&mb.level(Level::Debug).build()));
+        assert!(!logger.enabled(&mb.level(Level::Trace).build()));
+        assert_eq!(logger.state.read().unwrap().sinks.len(), 2);
+    }
+
+    #[test]
+    fn flush_guard_flushes_async_writes_on_drop() {
+        let path = TempPath::new("guard.log");
+
+        let logger = Logger::with_config(Config {
+            destination: Destination::File(FileConfig::new(&*path)),
+            async_writer: Some(AsyncConfig::default()),
+            ..Default::default()
+        });
+
+        {
+            let _guard = logger.handle().flush_guard();
+
+            for _ in 0..10 {
+                logger.log(
+                    &Record::builder()
+                        .args(format_args!("foo"))
+                        .level(Level::Info)
+                        .build(),
+                );
+            }
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 10);
+    }
+
+    #[test]
+    fn handle_rejects_invalid_pattern() {
+        let logger = Logger::new();
+        let handle = logger.handle();
+
+        assert!(handle
+            .set_record_format(RecordFormat::Pattern("{nope}".to_string()))
+            .is_err());
+        assert!(handle
+            .set_sink(
+                1,
+                SinkConfig {
+                    record_format: RecordFormat::Pattern("{".to_string()),
+                    ..Default::default()
+                },
+            )
+            .is_err());
+        assert_eq!(logger.state.read().unwrap().sinks.len(), 1);
+    }
+
+    #[test]
+    fn handle_replaces_config() {
+        let logger = Logger::new();
+        let handle = logger.handle();
+        let mut mb = Metadata::builder();
+
+        handle
+            .set_config(Config {
+                level: LevelFilter::Debug,
+                sinks: vec![SinkConfig::default(), SinkConfig::default()],
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(logger.enabled(&mb.level(Level::Debug).build()));
+        assert_eq!(logger.state.read().unwrap().sinks.len(), 2);
+
+        // invalid configs are rejected without changing the logger
+        assert!(handle
+            .set_config(Config {
+                level: LevelFilter::Error,
+                record_format: RecordFormat::Pattern("{nope}".to_string()),
+                ..Default::default()
+            })
+            .is_err());
+        assert!(logger.enabled(&mb.level(Level::Debug).build()));
+        assert_eq!(logger.state.read().unwrap().sinks.len(), 2);
+    }
+}
@@ -0,0 +1,272 @@
+//! Encoding of log records for systemd-journald's native protocol
+
+use crate::sculpt::key_values;
+use crate::syslog::severity;
+use log::Record;
+use serde_json::Value;
+
+#[cfg(target_os = "linux")]
+use std::{
+    ffi::c_void,
+    fs::File,
+    io::{self, Write},
+    mem,
+    os::unix::{
+        ffi::OsStrExt,
+        io::{AsRawFd, FromRawFd, RawFd},
+        net::UnixDatagram,
+    },
+    path::Path,
+    ptr,
+};
+
+/// Path of systemd-journald's native protocol socket
+pub const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Fields set from the record itself, which structured keys must not duplicate
+const RECORD_FIELDS: &[&str] = &[
+    "MESSAGE",
+    "PRIORITY",
+    "SYSLOG_IDENTIFIER",
+    "CODE_FILE",
+    "CODE_LINE",
+];
+
+/// Make a journal field name from a structured key (uppercase ASCII letters, digits and
+/// underscores, not starting with an underscore or digit, at most 64 characters)
+///
+/// Keys that would duplicate a field set from the record are prefixed with `KV_`
+///
+/// # Arguments
+///
+/// * `key` - the structured key
+fn field_name(key: &str) -> String {
+    let name: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = name.trim_start_matches('_');
+
+    // field names starting with `_` are reserved for trusted fields set by journald
+    let name = match name.chars().next() {
+        Some(c) if c.is_ascii_digit() => format!("F_{}", name),
+        Some(_) if RECORD_FIELDS.contains(&name) => format!("KV_{}", name),
+        Some(_) => name.to_string(),
+        None => "F".to_string(),
+    };
+
+    name.chars().take(64).collect()
+}
+
+/// Append a field to an entry, using the binary encoding if the value contains a newline
+///
+/// # Arguments
+///
+/// * `entry` - the encoded entry to append to
+/// * `name` - name of the field
+/// * `value` - value of the field
+fn push_field(entry: &mut Vec<u8>, name: &str, value: &str) {
+    entry.extend_from_slice(name.as_bytes());
+
+    if value.contains('\n') {
+        entry.push(b'\n');
+        entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        entry.push(b'=');
+    }
+
+    entry.extend_from_slice(value.as_bytes());
+    entry.push(b'\n');
+}
+
+/// Encode a log record as a journal entry
+///
+/// The formatted message is sent as `MESSAGE`, the level as `PRIORITY`, the target as
+/// `SYSLOG_IDENTIFIER`, the source location as `CODE_FILE`/`CODE_LINE`, and structured
+/// key-values as fields with uppercased names
+///
+/// # Arguments
+///
+/// * `record` - the log record to encode
+/// * `msg` - the formatted log message
+pub fn encode(record: &Record, msg: &str) -> Vec<u8> {
+    let mut entry = vec![];
+
+    push_field(&mut entry, "MESSAGE", msg);
+    push_field(
+        &mut entry,
+        "PRIORITY",
+        &severity(record.level()).to_string(),
+    );
+    push_field(&mut entry, "SYSLOG_IDENTIFIER", record.target());
+
+    if let Some(file) = record.file() {
+        push_field(&mut entry, "CODE_FILE", file);
+    }
+
+    if let Some(line) = record.line() {
+        push_field(&mut entry, "CODE_LINE", &line.to_string());
+    }
+
+    for (k, v) in key_values(record) {
+        let v = match v {
+            Value::String(s) => s,
+            v => v.to_string(),
+        };
+        push_field(&mut entry, &field_name(&k), &v);
+    }
+
+    entry
+}
+
+/// Check if sending an entry failed because it is too large for a single datagram
+///
+/// # Arguments
+///
+/// * `e` - error returned when sending the entry
+#[cfg(target_os = "linux")]
+pub fn is_too_large(e: &io::Error) -> bool {
+    matches!(e.raw_os_error(), Some(libc::EMSGSIZE) | Some(libc::ENOBUFS))
+}
+
+/// Send an entry that is too large for a single datagram the way journald expects: write
+/// it to a sealed memory file, then send the file's descriptor (with `SCM_RIGHTS`) in an
+/// otherwise empty datagram
+///
+/// # Arguments
+///
+/// * `socket` - socket to send the descriptor from
+/// * `path` - path of journald's socket
+/// * `entry` - the encoded entry
+#[cfg(target_os = "linux")]
+pub fn send_memfd(socket: &UnixDatagram, path: &Path, entry: &[u8]) -> io::Result<()> {
+    let fd = unsafe {
+        libc::memfd_create(
+            b"swing-journal\0".as_ptr() as *const libc::c_char,
+            libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING,
+        )
+    };
+
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // the file closes the descriptor when dropped, after it has been sent
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    file.write_all(entry)?;
+
+    // journald only accepts files that can no longer change
+    let seals = libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL;
+
+    if unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, seals) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut addr: libc::sockaddr_un = unsafe { mem::zeroed() };
+    let path = path.as_os_str().as_bytes();
+
+    if path.len() >= addr.sun_path.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "socket path is too long",
+        ));
+    }
+
+    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+
+    for (dst, src) in addr.sun_path.iter_mut().zip(path) {
+        *dst = *src as libc::c_char;
+    }
+
+    // u64s keep the control buffer aligned for `cmsghdr`
+    let space = unsafe { libc::CMSG_SPACE(mem::size_of::<RawFd>() as u32) } as usize;
+    let mut control = vec![0u64; space.div_ceil(mem::size_of::<u64>())];
+
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_name = &mut addr as *mut libc::sockaddr_un as *mut c_void;
+    msg.msg_namelen = (mem::size_of::<libc::sa_family_t>() + path.len() + 1) as libc::socklen_t;
+    msg.msg_control = control.as_mut_ptr() as *mut c_void;
+    msg.msg_controllen = space as _;
+
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<RawFd>() as u32) as _;
+        ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut RawFd, file.as_raw_fd());
+    }
+
+    if unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::{kv, Level};
+
+    #[test]
+    fn encode_maps_record_fields() {
+        let kvs: &[(&str, kv::Value)] = &[
+            ("user_id", kv::Value::from(42)),
+            ("_secret", kv::Value::from("x")),
+            ("2fa", kv::Value::from(true)),
+        ];
+        let rec = Record::builder()
+            .args(format_args!("foo"))
+            .level(Level::Warn)
+            .target("app::db")
+            .file(Some("src/db.rs"))
+            .line(Some(7))
+            .key_values(&kvs)
+            .build();
+
+        assert_eq!(
+            String::from_utf8(encode(&rec, "foo")).unwrap(),
+            "MESSAGE=foo\nPRIORITY=4\nSYSLOG_IDENTIFIER=app::db\nCODE_FILE=src/db.rs\n\
+             CODE_LINE=7\nUSER_ID=42\nSECRET=x\nF_2FA=true\n"
+        );
+    }
+
+    #[test]
+    fn encode_prefixes_keys_duplicating_record_fields() {
+        let kvs: &[(&str, kv::Value)] = &[
+            ("priority", kv::Value::from("high")),
+            ("message", kv::Value::from("x")),
+            ("syslog.identifier", kv::Value::from("y")),
+            ("code_file", kv::Value::from("z")),
+            ("_code_line", kv::Value::from(1)),
+            ("message_id", kv::Value::from(2)),
+        ];
+        let rec = Record::builder()
+            .level(Level::Error)
+            .target("t")
+            .key_values(&kvs)
+            .build();
+
+        assert_eq!(
+            String::from_utf8(encode(&rec, "foo")).unwrap(),
+            "MESSAGE=foo\nPRIORITY=3\nSYSLOG_IDENTIFIER=t\nKV_PRIORITY=high\nKV_MESSAGE=x\n\
+             KV_SYSLOG_IDENTIFIER=y\nKV_CODE_FILE=z\nKV_CODE_LINE=1\nMESSAGE_ID=2\n"
+        );
+    }
+
+    #[test]
+    fn encode_uses_binary_encoding_for_multi_line_values() {
+        let rec = Record::builder().level(Level::Info).target("t").build();
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&7u64.to_le_bytes());
+        expected.extend_from_slice(b"foo\nbar\nPRIORITY=6\nSYSLOG_IDENTIFIER=t\n");
+
+        assert_eq!(encode(&rec, "foo\nbar"), expected);
+    }
+}
@@ -7,32 +7,180 @@
 #![deny(missing_docs)]
 
 use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+mod file;
+mod filter;
+use filter::TargetFilter;
+use regex::Regex;
+mod handle;
+mod journal;
 mod paint;
-use paint::LogPainter;
+mod pattern;
+mod queue;
+use queue::WriteQueue;
 mod sculpt;
-use sculpt::LogSculptor;
+use sculpt::SculptOptions;
+mod sink;
+use sink::LogSink;
+mod socket;
+mod style;
+mod syslog;
+mod term;
+#[cfg(test)]
+mod testing;
+mod timestamp;
+use timestamp::Clock;
+mod watch;
 mod write;
-use write::LogWriter;
 
 pub mod color;
+pub mod description;
 pub mod theme;
-pub use color::{Color, Rgb, RgbRange};
-pub use paint::ColorFormat;
-pub use sculpt::RecordFormat;
+pub use color::{Color, ColorRamp, ColorSpace, ColorStop, Rgb, RgbRange};
+pub use file::{ArchiveNaming, FileConfig, RotationInterval};
+pub use filter::{Directive, FilterError, FilterSpec};
+pub use handle::{FlushGuard, LoggerHandle};
+pub use journal::JOURNALD_SOCKET;
+pub use paint::{ColorFormat, Easing, Gradient, HashKey, Quantization, Traversal};
+pub use queue::{AsyncConfig, OverflowPolicy};
+pub use sculpt::{FormatError, RecordFields, RecordFormat, Segment};
+pub use sink::SinkConfig;
+pub use style::{Style, StyleColor};
+pub use syslog::{Facility, SyslogConfig, SYSLOG_SOCKET};
+pub use term::ColorSupport;
 pub use theme::Theme;
+pub use timestamp::{TimeOffset, TimestampConfig, TimestampFormat};
+pub use watch::{ConfigWatcher, WATCHER_TARGET};
+pub use write::Destination;
 pub mod config;
 pub use config::Config;
+pub use description::{ConfigDescription, ConfigError, ConfigFormat};
+
+/// Filters and pipeline used by a `Logger`, shared with its `LoggerHandle`s
+struct LoggerState {
+    /// Level and target based filter for logs
+    target_filter: TargetFilter,
+    /// Regex that log messages must match
+    message_filter: Option<Regex>,
+    /// settings for the built-in record formats, shared by all sinks
+    sculpt_options: SculptOptions,
+    /// sinks that each record is written to
+    sinks: Vec<LogSink>,
+    /// queue for writing records on a background thread (`None` writes on the logging thread)
+    queue: Option<WriteQueue>,
+    /// whether the logger has been installed as the global logger by `Logger::init`
+    installed: AtomicBool,
+}
+
+impl LoggerState {
+    /// Create the filters and pipeline for a configuration, or return an error if a
+    /// record or timestamp format in `config` is invalid
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - configuration of the logger
+    fn new(config: Config) -> Result<LoggerState, FormatError> {
+        let sinks = if config.sinks.is_empty() {
+            vec![SinkConfig {
+                level: LevelFilter::Trace,
+                record_format: config.record_format,
+                color_format: config.color_format,
+                theme: config.theme,
+                use_stderr: config.use_stderr,
+                destination: config.destination,
+                color_support: config.color_support,
+                quantization: config.quantization,
+            }]
+        } else {
+            config.sinks
+        };
+
+        let sculpt_options = SculptOptions {
+            json_kv_key: config.json_kv_key,
+            clock: Clock::new(config.timestamp.as_ref())?,
+            fields: config.record_fields,
+        };
+
+        Ok(LoggerState {
+            target_filter: TargetFilter::new(config.level, config.directives),
+            message_filter: config.message_filter,
+            sinks: sinks
+                .into_iter()
+                .map(|s| LogSink::new(s, &sculpt_options))
+                .collect::<Result<_, _>>()?,
+            sculpt_options,
+            queue: config.async_writer.map(WriteQueue::new),
+            installed: AtomicBool::new(false),
+        })
+    }
+
+    /// Log a record that passes the filters to every sink
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - the record to log
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        if let Some(re) = &self.message_filter {
+            if !re.is_match(&record.args().to_string()) {
+                return;
+            }
+        }
+
+        for sink in self.sinks.iter() {
+            sink.log(record, self.queue.as_ref());
+        }
+    }
+
+    /// Wait for queued records to be written, then flush every sink's writer
+    fn flush(&self) {
+        if let Some(queue) = &self.queue {
+            queue.flush();
+        }
+
+        for sink in self.sinks.iter() {
+            sink.log_writer.flush();
+        }
+    }
+
+    /// Check if a record with this metadata passes the filters and would be written by any sink
+    ///
+    /// # Arguments
+    ///
+    /// * `metadata` - metadata of the record to check
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.target_filter.enabled(metadata)
+            && self.sinks.iter().any(|s| s.enabled(metadata.level()))
+    }
+
+    /// Update the global maximum log level to match this logger's filters, if it is the
+    /// installed logger (handles to other loggers must not change the global level)
+    fn update_max_level(&self) {
+        if self.installed.load(Ordering::SeqCst) {
+            log::set_max_level(self.max_level());
+        }
+    }
+
+    /// Get the most verbose level that any sink will write
+    fn max_level(&self) -> LevelFilter {
+        let sinks_max = self.sinks.iter().map(|s| s.level).max();
+        self.target_filter
+            .max_level()
+            .min(sinks_max.unwrap_or(LevelFilter::Off))
+    }
+}
 
 /// Implements log::Log
 pub struct Logger {
-    /// Level-based filter for logs
-    level_filter: LevelFilter,
-    /// painter for logs
-    log_painter: LogPainter,
-    /// sculptor for logs
-    log_sculptor: LogSculptor,
-    /// writer for logs
-    log_writer: LogWriter,
+    /// state shared with handles to this logger
+    ///
+    /// records are logged while holding a read lock, so reconfiguring the logger
+    /// through a handle (write lock) waits for in-flight records to finish
+    state: Arc<RwLock<LoggerState>>,
 }
 
 impl Logger {
@@ -46,25 +194,72 @@ impl Logger {
     /// # Arguments
     ///
     /// * `config` - configuration for this logger
+    ///
+    /// # Panics
+    ///
+    /// Panics if a record or timestamp format in `config` is invalid (see
+    /// `Logger::try_with_config`)
     pub fn with_config(config: Config) -> Logger {
-        Logger {
-            log_sculptor: LogSculptor::new(config.record_format),
-            level_filter: config.level,
-            log_painter: LogPainter::new(config.theme, config.color_format),
-            log_writer: LogWriter::new(config.use_stderr),
-        }
+        Logger::try_with_config(config).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Create a new Logger with a custom configuration, or return an error if a record
+    /// or timestamp format in `config` is invalid (e.g. a `RecordFormat::Pattern` with an
+    /// unknown placeholder)
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - configuration for this logger
+    pub fn try_with_config(config: Config) -> Result<Logger, FormatError> {
+        Ok(Logger {
+            state: Arc::new(RwLock::new(LoggerState::new(config)?)),
+        })
+    }
+
+    /// Create a new Logger with filters read from an environment variable
+    ///
+    /// See `Config::from_env` for details
+    ///
+    /// # Arguments
+    ///
+    /// * `var` - name of the environment variable to read filters from (e.g. `RUST_LOG`)
+    pub fn from_env(var: &str) -> Result<Logger, FilterError> {
+        Config::from_env(var).map(Logger::with_config)
+    }
+
+    /// Get a handle that can reconfigure this logger at runtime
+    pub fn handle(&self) -> LoggerHandle {
+        LoggerHandle::new(Arc::clone(&self.state))
     }
 
     /// Initialize this logger
-    pub fn init(self) -> Result<(), SetLoggerError> {
-        log::set_boxed_logger(Box::new(self)).map(|()| log::set_max_level(LevelFilter::Trace))
+    ///
+    /// The global maximum log level is set to the most verbose level allowed
+    /// by this logger's level filters.  The returned handle can be used to
+    /// reconfigure the logger after it has been installed.
+    pub fn init(self) -> Result<LoggerHandle, SetLoggerError> {
+        let handle = self.handle();
+        let state = Arc::clone(&self.state);
+        log::set_boxed_logger(Box::new(self)).map(|()| {
+            let state = state.read().unwrap();
+            state.installed.store(true, Ordering::SeqCst);
+            state.update_max_level();
+            handle
+        })
+    }
+}
+
+impl Default for Logger {
+    /// Create a new Logger with a default configuration
+    fn default() -> Logger {
+        Logger::new()
     }
 }
 
 impl Log for Logger {
     /// Check if this message should be logged
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= self.level_filter
+        self.state.read().unwrap().enabled(metadata)
     }
 
     /// Log a message/record
@@ -73,22 +268,21 @@ impl Log for Logger {
     ///
     /// * `record` - the record to log
     fn log(&self, record: &Record) {
-        if !self.enabled(record.metadata()) {
-            return;
-        }
-
-        let mut msg = self.log_sculptor.sculpt(record);
-        msg = self.log_painter.paint(msg, record.level());
-        self.log_writer.write(msg, record.level());
+        self.state.read().unwrap().log(record);
     }
 
-    fn flush(&self) {}
+    /// Wait for records queued for asynchronous writing to be written, then
+    /// flush every underlying writer
+    fn flush(&self) {
+        self.state.read().unwrap().flush();
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use log::Level;
+    use crate::testing::TempPath;
+    use log::{Level, LevelFilter};
 
     #[test]
     fn enabled_filters_levels() {
@@ -99,11 +293,11 @@ mod tests {
         let logger = Logger::with_config(config);
         let mut mb = Metadata::builder();
 
-        assert!(!logger.enabled(&mut mb.level(Level::Trace).build()));
-        assert!(!logger.enabled(&mut mb.level(Level::Debug).build()));
-        assert!(!logger.enabled(&mut mb.level(Level::Info).build()));
-        assert!(logger.enabled(&mut mb.level(Level::Warn).build()));
-        assert!(logger.enabled(&mut mb.level(Level::Error).build()));
+        assert!(!logger.enabled(&mb.level(Level::Trace).build()));
+        assert!(!logger.enabled(&mb.level(Level::Debug).build()));
+        assert!(!logger.enabled(&mb.level(Level::Info).build()));
+        assert!(logger.enabled(&mb.level(Level::Warn).build()));
+        assert!(logger.enabled(&mb.level(Level::Error).build()));
     }
 
     #[test]
@@ -115,11 +309,91 @@ mod tests {
         let logger = Logger::with_config(config);
         let mut mb = Metadata::builder();
 
-        assert!(!logger.enabled(&mut mb.level(Level::Trace).build()));
-        assert!(!logger.enabled(&mut mb.level(Level::Debug).build()));
-        assert!(!logger.enabled(&mut mb.level(Level::Info).build()));
-        assert!(!logger.enabled(&mut mb.level(Level::Warn).build()));
-        assert!(!logger.enabled(&mut mb.level(Level::Error).build()));
+        assert!(!logger.enabled(&mb.level(Level::Trace).build()));
+        assert!(!logger.enabled(&mb.level(Level::Debug).build()));
+        assert!(!logger.enabled(&mb.level(Level::Info).build()));
+        assert!(!logger.enabled(&mb.level(Level::Warn).build()));
+        assert!(!logger.enabled(&mb.level(Level::Error).build()));
+    }
+
+    #[test]
+    fn enabled_uses_target_directives() {
+        let config = Config {
+            level: LevelFilter::Warn,
+            directives: vec![
+                Directive {
+                    target: "our_crate::db".to_string(),
+                    level: LevelFilter::Debug,
+                },
+                Directive {
+                    target: "hyper".to_string(),
+                    level: LevelFilter::Off,
+                },
+            ],
+            ..Default::default()
+        };
+        let logger = Logger::with_config(config);
+        let mut mb = Metadata::builder();
+
+        assert!(logger.enabled(&mb.level(Level::Debug).target("our_crate::db").build()));
+        assert!(!logger.enabled(&mb.level(Level::Info).target("our_crate").build()));
+        assert!(logger.enabled(&mb.level(Level::Warn).target("our_crate").build()));
+        assert!(!logger.enabled(&mb.level(Level::Error).target("hyper::client").build()));
+    }
+
+    #[test]
+    fn enabled_checks_sink_levels() {
+        let config = Config {
+            level: LevelFilter::Debug,
+            sinks: vec![
+                SinkConfig {
+                    level: LevelFilter::Warn,
+                    ..Default::default()
+                },
+                SinkConfig {
+                    level: LevelFilter::Info,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let logger = Logger::with_config(config);
+        let mut mb = Metadata::builder();
+
+        // records must pass the logger's filters and at least one sink's level
+        assert!(!logger.enabled(&mb.level(Level::Debug).build()));
+        assert!(logger.enabled(&mb.level(Level::Info).build()));
+        assert_eq!(logger.state.read().unwrap().max_level(), LevelFilter::Info);
+    }
+
+    #[test]
+    fn flush_waits_for_async_writes() {
+        let path = TempPath::new("async.log");
+
+        let config = Config {
+            record_format: RecordFormat::Custom(Box::new(|r| r.args().to_string())),
+            destination: Destination::File(FileConfig::new(&*path)),
+            async_writer: Some(AsyncConfig {
+                capacity: 2,
+                overflow: OverflowPolicy::Block,
+            }),
+            ..Default::default()
+        };
+        let logger = Logger::with_config(config);
+
+        for i in 0..20 {
+            logger.log(
+                &Record::builder()
+                    .args(format_args!("{}", i))
+                    .level(Level::Info)
+                    .build(),
+            );
+        }
+        logger.flush();
+
+        let expected = (0..20).map(|i| format!("{}\n", i)).collect::<String>();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), expected);
+        assert_eq!(logger.handle().dropped_records(), 0);
     }
 
     #[test]
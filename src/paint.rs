@@ -1,6 +1,10 @@
-use crate::{Rgb, RgbRange, Theme};
-use colored::Colorize;
-use log::Level;
+use crate::color::{ColorRamp, ColorSpace};
+use crate::sculpt::{thread_name, Segment, SegmentedRecord};
+use crate::style::{Style, StyleColor};
+use crate::term::ColorSupport;
+use crate::{Rgb, Theme};
+use log::{Level, Record};
+use serde::Deserialize;
 use std::collections::HashMap;
 use std::sync::Mutex;
 use unicode_segmentation::UnicodeSegmentation;
@@ -10,13 +14,162 @@ use unicode_segmentation::UnicodeSegmentation;
 pub enum ColorFormat {
     /// solid color(s) applied from a theme to log lines
     Solid,
-    /// linear color gradient applied over characters in a single line, with arg for number of steps
-    /// in gradient (how many characters it will take to go from the starting color to the ending color
-    /// for each level)
-    InlineGradient(usize),
-    /// linear color gradient applied over multiple lines, with arg for number of steps in gradient (how
-    /// many lines it will take to go from the starting color to the ending color for each level)
-    MultiLineGradient(usize),
+    /// linear color gradient applied over characters in a single line, where the gradient's
+    /// steps are how many characters it will take to go from the starting color to the ending
+    /// color for each level
+    InlineGradient(Gradient),
+    /// linear color gradient applied over multiple lines, where the gradient's steps are how
+    /// many lines it will take to go from the starting color to the ending color for each level
+    MultiLineGradient(Gradient),
+    /// styles applied to each segment of a record independently (e.g. a dim timestamp, a
+    /// bold colored level and a plain message), as chosen by the theme's `segment_style`
+    ///
+    /// Only `Simple` and `Pattern` records are split into segments, records in other
+    /// formats are styled as a single message
+    Segmented,
+    /// like `Segmented`, but with the level drawn as a badge (see `Style::badge`), e.g.
+    /// white on red ` ERROR `, to make levels stand out in dense output
+    Badged,
+    /// solid color per line, chosen by hashing the record's target or thread name, so that
+    /// lines from the same component always get the same color (see `Theme::palette`)
+    Hashed(HashKey),
+}
+
+/// Settings for gradient color formats
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Gradient {
+    /// number of steps in the gradient
+    pub steps: usize,
+    /// color space that colors along the gradient are interpolated in
+    #[serde(default)]
+    pub space: ColorSpace,
+    /// how the gradient is traversed once its end color is reached
+    #[serde(default)]
+    pub traversal: Traversal,
+    /// curve that maps steps to distances along the gradient
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+impl Gradient {
+    /// Create a new Gradient, interpolated in sRGB and oscillating linearly between its
+    /// start and end colors
+    ///
+    /// # Arguments
+    ///
+    /// * `steps` - number of steps in the gradient
+    pub fn new(steps: usize) -> Gradient {
+        Gradient {
+            steps,
+            space: ColorSpace::Srgb,
+            traversal: Traversal::Oscillate,
+            easing: Easing::Linear,
+        }
+    }
+
+    /// Get the distance, [0-1], along this gradient at step `x`
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - step number (e.g. grapheme or line index)
+    fn dist(&self, x: usize) -> f32 {
+        let dist = match self.traversal {
+            Traversal::Oscillate => oscillate_dist(x, self.steps),
+            Traversal::Wrap => wrap_dist(x, self.steps),
+            Traversal::Once => once_dist(x, self.steps),
+        };
+
+        self.easing.apply(dist)
+    }
+}
+
+/// How a gradient is traversed once its end color is reached
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Traversal {
+    /// alternate between going from the start to the end color and back (ping-pong)
+    #[default]
+    Oscillate,
+    /// jump back to the start color after the end color (sawtooth)
+    Wrap,
+    /// stay at the end color
+    Once,
+}
+
+/// Curve that maps steps to distances along a gradient, changing how quickly colors change
+/// near its start and end
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    /// constant speed
+    #[default]
+    Linear,
+    /// start slowly, then speed up (quadratic)
+    EaseIn,
+    /// start quickly, then slow down (quadratic)
+    EaseOut,
+    /// start and end slowly (quadratic)
+    EaseInOut,
+    /// start and end slowly, following half a cosine wave
+    Sine,
+    /// start and end slowly, more sharply than `EaseInOut` (cubic)
+    Cubic,
+}
+
+impl Easing {
+    /// Map a linear distance to an eased distance
+    ///
+    /// # Arguments
+    ///
+    /// * `t` - linear distance (0.0 - 1.0)
+    fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - 2.0 * (1.0 - t) * (1.0 - t)
+                }
+            }
+            Easing::Sine => (1.0 - (std::f32::consts::PI * t).cos()) / 2.0,
+            Easing::Cubic => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - 4.0 * (1.0 - t).powi(3)
+                }
+            }
+        }
+    }
+}
+
+/// Part of a record that the `Hashed` color format chooses colors by
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashKey {
+    /// target of the record (usually its module path)
+    Target,
+    /// name (or id, if unnamed) of the thread that logged the record
+    Thread,
+}
+
+/// How colors are quantized for terminals that can't display 24-bit colors
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Quantization {
+    /// use the perceptually nearest color in the terminal's palette
+    #[default]
+    Nearest,
+    /// carry the difference between each grapheme's color and its nearest palette color
+    /// over to the next grapheme of inline gradients (error diffusion), so gradients
+    /// blend between palette colors instead of collapsing into bands
+    Dither,
 }
 
 /// Get the distance, [0-1], that `x` falls along the line from 0-`n`
@@ -38,28 +191,147 @@ fn oscillate_dist(x: usize, n: usize) -> f32 {
     (x.wrapping_add(n) % n.wrapping_mul(2)).abs_diff(n) as f32 / (n as f32)
 }
 
+/// Get the distance, [0-1], that `x` falls along the line from 0-`n`, restarting at 0
+/// after reaching 1
+///
+/// # Arguments
+///
+/// * `x` - some number whose value, `x` % (`n` + 1), will be considered the distance along
+///   the line 0-`n`
+/// * `n` - upper limit of range 0-`n`
+fn wrap_dist(x: usize, n: usize) -> f32 {
+    let n = n.max(1);
+    (x % n.saturating_add(1)) as f32 / (n as f32)
+}
+
+/// Get the distance, [0-1], that `x` falls along the line from 0-`n`, staying at 1
+/// after reaching it
+///
+/// # Arguments
+///
+/// * `x` - some number whose value will be considered the distance along the line 0-`n`
+/// * `n` - upper limit of range 0-`n`
+fn once_dist(x: usize, n: usize) -> f32 {
+    let n = n.max(1);
+    x.min(n) as f32 / (n as f32)
+}
+
 /// Compute a new color `dist` distance along the linear
-/// gradient from start to end of `range`
+/// gradient through the stops of `ramp`
 ///
 /// `dist` will be clamped to the range 0.0 - 1.0
 ///
 /// # Arguments
 ///
-/// * `range` - bounding color range for this linear gradient
+/// * `ramp` - color stops for this linear gradient
 /// * `dist` - desired distance along linear gradient (0.0 - 1.0)
-fn linear_gradient(range: &RgbRange, dist: f32) -> Rgb {
-    let dist = dist.clamp(0.0, 1.0);
-    let start = &range.start;
-    let end = &range.end;
+/// * `space` - color space to interpolate in
+fn linear_gradient(ramp: &ColorRamp, dist: f32, space: ColorSpace) -> Rgb {
+    ramp.sample(dist.clamp(0.0, 1.0), space)
+}
+
+/// Hash a string with 64-bit FNV-1a, which (unlike `std`'s hashers) is the same across
+/// runs, platforms, and Rust versions
+///
+/// # Arguments
+///
+/// * `s` - string to hash
+fn stable_hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Get the number of the nearest color in a stream's palette
+///
+/// # Arguments
+///
+/// * `color` - color to quantize
+/// * `support` - colors supported by the stream (`Ansi16` or `Ansi256`)
+fn quantize(color: Rgb, support: ColorSupport) -> u8 {
+    match support {
+        ColorSupport::Ansi16 => color.to_ansi16(),
+        _ => color.to_ansi256(),
+    }
+}
+
+/// Quantize a color, after adding the error carried over from the previous grapheme
+///
+/// # Arguments
+///
+/// * `color` - color to quantize
+/// * `err` - quantization error carried over, updated with the error of this color
+/// * `support` - colors supported by the stream (`Ansi16` or `Ansi256`)
+fn dither(color: Rgb, err: &mut [f32; 3], support: ColorSupport) -> u8 {
+    let channel = |c: u8, e: f32| (c as f32 + e).round().clamp(0.0, 255.0) as u8;
+    let wanted = Rgb {
+        r: channel(color.r, err[0]),
+        g: channel(color.g, err[1]),
+        b: channel(color.b, err[2]),
+    };
+    let n = quantize(wanted, support);
+    let shown = Rgb::from_ansi256(n);
+
+    *err = [
+        wanted.r as f32 - shown.r as f32,
+        wanted.g as f32 - shown.g as f32,
+        wanted.b as f32 - shown.b as f32,
+    ];
+    n
+}
+
+/// Get the SGR parameter for a color number from a stream's palette
+///
+/// # Arguments
+///
+/// * `n` - palette color number
+/// * `support` - colors supported by the stream (`Ansi16` or `Ansi256`)
+/// * `background` - whether the color is a background color
+fn indexed_code(n: u8, support: ColorSupport, background: bool) -> String {
+    let base = if background { 40 } else { 30 };
 
-    let r_range = (end.r as f32) - (start.r as f32);
-    let g_range = (end.g as f32) - (start.g as f32);
-    let b_range = (end.b as f32) - (start.b as f32);
+    match (support, n) {
+        (ColorSupport::Ansi16, 0..=7) => (base + n).to_string(),
+        (ColorSupport::Ansi16, _) => (base + 52 + n).to_string(),
+        _ => format!("{};5;{}", base + 8, n),
+    }
+}
 
-    Rgb {
-        r: ((start.r as f32) + (dist * r_range)) as u8,
-        g: ((start.g as f32) + (dist * g_range)) as u8,
-        b: ((start.b as f32) + (dist * b_range)) as u8,
+/// Get the SGR parameter for the nearest color that a stream supports, or `None` if it
+/// doesn't support colors
+///
+/// # Arguments
+///
+/// * `color` - color to convert
+/// * `support` - colors supported by the stream
+/// * `background` - whether the color is a background color
+fn color_code(color: Rgb, support: ColorSupport, background: bool) -> Option<String> {
+    match support {
+        ColorSupport::Off => None,
+        ColorSupport::Ansi16 | ColorSupport::Ansi256 => {
+            Some(indexed_code(quantize(color, support), support, background))
+        }
+        ColorSupport::TrueColor => Some(format!(
+            "{};2;{};{};{}",
+            if background { 48 } else { 38 },
+            color.r,
+            color.g,
+            color.b
+        )),
+    }
+}
+
+/// Wrap a string in an SGR escape sequence with the given parameters, followed by a reset
+///
+/// # Arguments
+///
+/// * `s` - string to wrap
+/// * `codes` - SGR parameters (the string is returned as-is if there are none)
+fn sgr(s: &str, codes: &[String]) -> String {
+    if codes.is_empty() {
+        s.to_string()
+    } else {
+        format!("\x1b[{}m{}\x1b[0m", codes.join(";"), s)
     }
 }
 
@@ -72,6 +344,8 @@ pub struct LogPainter {
     theme: Box<dyn Theme>,
     /// color formatting mode (determines how log records are colored)
     color_format: Option<ColorFormat>,
+    /// how colors are quantized for terminals without 24-bit color
+    quantization: Quantization,
 }
 
 impl LogPainter {
@@ -86,25 +360,73 @@ impl LogPainter {
             lines_logged: Mutex::new(HashMap::new()),
             theme,
             color_format,
+            quantization: Quantization::default(),
         }
     }
 
+    /// Replace the theme used for color selection
+    ///
+    /// # Arguments
+    ///
+    /// * `theme` - new theme
+    pub fn set_theme(&mut self, theme: Box<dyn Theme>) {
+        self.theme = theme;
+    }
+
+    /// Replace the format used for painting
+    ///
+    /// Multi-line gradients continue from the current line counts
+    ///
+    /// # Arguments
+    ///
+    /// * `color_format` - new color format
+    pub fn set_color_format(&mut self, color_format: Option<ColorFormat>) {
+        self.color_format = color_format;
+    }
+
+    /// Replace the way colors are quantized for terminals without 24-bit color
+    ///
+    /// # Arguments
+    ///
+    /// * `quantization` - new quantization mode
+    pub fn set_quantization(&mut self, quantization: Quantization) {
+        self.quantization = quantization;
+    }
+
     /// Paint/color a log line, based on the current logger configuration
     ///
+    /// Colors are downgraded to the nearest ones that the output stream supports.  Lines
+    /// without a color format keep the text attributes of the theme's `level_style`
+    ///
     /// Arguments
     ///
     /// * `msg` - message to paint/color
     /// * `level` - level of this log line
-    pub fn paint(&self, msg: String, level: Level) -> String {
-        if self.color_format.is_none() {
+    /// * `support` - colors supported by the stream this line is written to
+    pub fn paint(&self, msg: String, level: Level, support: ColorSupport) -> String {
+        if support == ColorSupport::Off {
             return msg;
         }
 
-        let line = match self.color_format.as_ref().unwrap() {
-            ColorFormat::Solid => self.paint_solid(msg, level),
-            ColorFormat::InlineGradient(steps) => self.paint_inline_gradient(msg, level, *steps),
-            ColorFormat::MultiLineGradient(steps) => {
-                let l = self.paint_multi_line_gradient(msg, level, *steps);
+        let color_format = match &self.color_format {
+            Some(color_format) => color_format,
+            None => {
+                let style = Style {
+                    fg: None,
+                    bg: None,
+                    ..self.theme.level_style(level)
+                };
+                return self.paint_styled(&msg, &style, level, support);
+            }
+        };
+
+        match color_format {
+            ColorFormat::Solid => self.paint_solid(msg, level, support),
+            ColorFormat::InlineGradient(gradient) => {
+                self.paint_inline_gradient(msg, level, gradient, support)
+            }
+            ColorFormat::MultiLineGradient(gradient) => {
+                let l = self.paint_multi_line_gradient(msg, level, gradient, support);
 
                 // increment line counter for this level
                 self.lines_logged
@@ -114,11 +436,164 @@ impl LogPainter {
                     .and_modify(|e| *e = e.wrapping_add(1))
                     .or_insert(0);
 
-                return l;
+                l
+            }
+            ColorFormat::Segmented | ColorFormat::Badged => {
+                self.paint_segments(msg.into(), level, support)
+            }
+            // there is no record to hash
+            ColorFormat::Hashed(_) => self.paint_solid(msg, level, support),
+        }
+    }
+
+    /// Paint/color a sculpted record, styling each of its segments independently if the
+    /// color format is `Segmented` or `Badged`, or painting its text as a whole otherwise
+    /// (with a color hashed from the record for the `Hashed` format)
+    ///
+    /// # Arguments
+    ///
+    /// * `sculpted` - sculpted record to paint/color
+    /// * `record` - the log record it was sculpted from
+    /// * `support` - colors supported by the stream this line is written to
+    pub fn paint_sculpted(
+        &self,
+        sculpted: SegmentedRecord,
+        record: &Record,
+        support: ColorSupport,
+    ) -> String {
+        let level = record.level();
+
+        match &self.color_format {
+            Some(ColorFormat::Segmented | ColorFormat::Badged) => {
+                self.paint_segments(sculpted, level, support)
+            }
+            Some(ColorFormat::Hashed(key)) if support != ColorSupport::Off => {
+                let color = match key {
+                    HashKey::Target => self.hashed_color(record.target(), level),
+                    HashKey::Thread => self.hashed_color(&thread_name(), level),
+                };
+                let style = self.line_style(level, StyleColor::Rgb(color));
+                self.paint_styled(&sculpted.into_text(), &style, level, support)
             }
+            _ => self.paint(sculpted.into_text(), level, support),
+        }
+    }
+
+    /// Style each segment of a sculpted record independently, as chosen by the theme's
+    /// `segment_style` (or as a badge for levels in the `Badged` format)
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - sculpted record to paint/color
+    /// * `level` - level of this log line
+    /// * `support` - colors supported by the stream this line is written to
+    fn paint_segments(
+        &self,
+        record: SegmentedRecord,
+        level: Level,
+        support: ColorSupport,
+    ) -> String {
+        let badged = matches!(self.color_format, Some(ColorFormat::Badged));
+
+        if support == ColorSupport::Off {
+            return record.into_text();
+        }
+
+        record
+            .segments()
+            .iter()
+            .map(|(segment, text)| {
+                let style = match segment {
+                    Segment::Level if badged => Style::badge(),
+                    _ => self.theme.segment_style(level, *segment),
+                };
+                self.paint_styled(text, &style, level, support)
+            })
+            .collect()
+    }
+
+    /// Get the color of lines with a hash key at a level, from the theme's palette, or
+    /// along its ramp if the palette is empty
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - the record's target or thread name
+    /// * `level` - level of this log line
+    fn hashed_color(&self, key: &str, level: Level) -> Rgb {
+        let hash = stable_hash(key);
+        let palette = self.theme.palette(level);
+
+        if palette.is_empty() {
+            // top 24 bits of the hash, as a distance along the ramp
+            let dist = (hash >> 40) as f32 / ((1 << 24) - 1) as f32;
+            linear_gradient(&self.theme.ramp(level), dist, ColorSpace::Oklab)
+        } else {
+            palette[(hash % palette.len() as u64) as usize]
+        }
+    }
+
+    /// Get the theme's style for whole lines at a level, with the foreground color chosen
+    /// by the color format
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - level of this log line
+    /// * `fg` - foreground color of the line
+    fn line_style(&self, level: Level, fg: StyleColor) -> Style {
+        Style {
+            fg: Some(fg),
+            ..self.theme.level_style(level)
+        }
+    }
+
+    /// Get the SGR parameters for a style
+    ///
+    /// # Arguments
+    ///
+    /// * `style` - style to convert
+    /// * `level` - level of this log line (for `StyleColor::Level`)
+    /// * `support` - colors supported by the output stream
+    fn style_codes(&self, style: &Style, level: Level, support: ColorSupport) -> Vec<String> {
+        let solid = || self.theme.solid(level);
+        let resolve = |color: StyleColor, other: Option<StyleColor>| match color {
+            StyleColor::Level => solid(),
+            StyleColor::Rgb(color) => color,
+            StyleColor::Contrast => match other {
+                Some(StyleColor::Rgb(color)) => color.contrasting(),
+                _ => solid().contrasting(),
+            },
         };
 
-        return line;
+        let mut codes = style.attribute_codes();
+        codes.extend(
+            style
+                .bg
+                .and_then(|c| color_code(resolve(c, style.fg), support, true)),
+        );
+        codes.extend(
+            style
+                .fg
+                .and_then(|c| color_code(resolve(c, style.bg), support, false)),
+        );
+        codes
+    }
+
+    /// Apply a style to a string
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - string to style
+    /// * `style` - style to apply
+    /// * `level` - level of this log line (for `StyleColor::Level`)
+    /// * `support` - colors supported by the output stream
+    fn paint_styled(&self, s: &str, style: &Style, level: Level, support: ColorSupport) -> String {
+        let codes = self.style_codes(style, level, support);
+
+        if style.pad {
+            sgr(&format!(" {} ", s), &codes)
+        } else {
+            sgr(s, &codes)
+        }
     }
 
     /// Paint strings using one color per line,
@@ -128,9 +603,10 @@ impl LogPainter {
     ///
     /// * `msg` - message to color
     /// * `level` - level of this log line
-    fn paint_solid(&self, msg: String, level: Level) -> String {
-        let color = self.theme.solid(level);
-        msg.color(color).to_string()
+    /// * `support` - colors supported by the output stream
+    fn paint_solid(&self, msg: String, level: Level, support: ColorSupport) -> String {
+        let style = self.line_style(level, StyleColor::Level);
+        self.paint_styled(&msg, &style, level, support)
     }
 
     /// Apply linear color gradient across the graphemes in a string
@@ -139,14 +615,45 @@ impl LogPainter {
     ///
     /// * `msg` - message to color
     /// * `level` - level of this log line
-    /// * `steps` - number of steps in gradient
-    fn paint_inline_gradient(&self, msg: String, level: Level, steps: usize) -> String {
+    /// * `gradient` - steps and color space of the gradient
+    /// * `support` - colors supported by the output stream
+    fn paint_inline_gradient(
+        &self,
+        msg: String,
+        level: Level,
+        gradient: &Gradient,
+        support: ColorSupport,
+    ) -> String {
+        let dithered = self.quantization == Quantization::Dither
+            && matches!(support, ColorSupport::Ansi16 | ColorSupport::Ansi256);
+        let mut err = [0.0; 3];
+        let ramp = self.theme.ramp(level);
+
+        // text attributes and background are the same for every grapheme
+        let style = Style {
+            fg: None,
+            ..self.theme.level_style(level)
+        };
+        let line_codes = self.style_codes(&style, level, support);
+
         msg.graphemes(true)
             .enumerate()
             .map(|(i, c)| {
-                let dist = oscillate_dist(i, steps);
-                let color = linear_gradient(&self.theme.range(level), dist);
-                c.color(color).to_string()
+                let dist = gradient.dist(i);
+                let color = linear_gradient(&ramp, dist, gradient.space);
+                let mut codes = line_codes.clone();
+
+                if dithered {
+                    codes.push(indexed_code(
+                        dither(color, &mut err, support),
+                        support,
+                        false,
+                    ));
+                } else {
+                    codes.extend(color_code(color, support, false));
+                }
+
+                sgr(c, &codes)
             })
             .collect::<Vec<String>>()
             .join("")
@@ -163,19 +670,28 @@ impl LogPainter {
     ///
     /// * `msg` - message to color
     /// * `level` - level of this log line
-    /// * `steps` - number of steps in gradient
-    fn paint_multi_line_gradient(&self, msg: String, level: Level, steps: usize) -> String {
+    /// * `gradient` - steps and color space of the gradient
+    /// * `support` - colors supported by the output stream
+    fn paint_multi_line_gradient(
+        &self,
+        msg: String,
+        level: Level,
+        gradient: &Gradient,
+        support: ColorSupport,
+    ) -> String {
         let lines_logged = *self.lines_logged.lock().unwrap().entry(level).or_insert(0);
-        let dist = oscillate_dist(lines_logged, steps);
-        let color = linear_gradient(&self.theme.range(level), dist);
-        msg.color(color).to_string()
+        let dist = gradient.dist(lines_logged);
+        let color = linear_gradient(&self.theme.ramp(level), dist, gradient.space);
+        let style = self.line_style(level, StyleColor::Rgb(color));
+        self.paint_styled(&msg, &style, level, support)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::theme;
+    use crate::color::ColorStop;
+    use crate::{theme, RgbRange};
     use num::NumCast;
 
     // helpers
@@ -246,6 +762,16 @@ mod tests {
         }
     }
 
+    /// Build a record with no message
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - level of the record
+    /// * `target` - target of the record
+    fn log_record(level: Level, target: &str) -> Record<'_> {
+        Record::builder().level(level).target(target).build()
+    }
+
     // tests
 
     #[test]
@@ -259,9 +785,13 @@ mod tests {
             },
         };
 
-        assert_rgb_eq(linear_gradient(&r, 0.0), Rgb { r: 0, g: 0, b: 0 }, None);
         assert_rgb_eq(
-            linear_gradient(&r, 0.25),
+            linear_gradient(&r.into(), 0.0, ColorSpace::Srgb),
+            Rgb { r: 0, g: 0, b: 0 },
+            None,
+        );
+        assert_rgb_eq(
+            linear_gradient(&r.into(), 0.25, ColorSpace::Srgb),
             Rgb {
                 r: 64,
                 g: 64,
@@ -270,7 +800,7 @@ mod tests {
             None,
         );
         assert_rgb_eq(
-            linear_gradient(&r, 0.5),
+            linear_gradient(&r.into(), 0.5, ColorSpace::Srgb),
             Rgb {
                 r: 128,
                 g: 128,
@@ -279,7 +809,7 @@ mod tests {
             None,
         );
         assert_rgb_eq(
-            linear_gradient(&r, 0.75),
+            linear_gradient(&r.into(), 0.75, ColorSpace::Srgb),
             Rgb {
                 r: 190,
                 g: 190,
@@ -288,7 +818,7 @@ mod tests {
             None,
         );
         assert_rgb_eq(
-            linear_gradient(&r, 1.0),
+            linear_gradient(&r.into(), 1.0, ColorSpace::Srgb),
             Rgb {
                 r: 255,
                 g: 255,
@@ -310,14 +840,138 @@ mod tests {
         };
 
         let expected = Rgb { r: 0, g: 0, b: 0 };
-        assert_rgb_eq(linear_gradient(&r, -1.0), expected, None);
+        assert_rgb_eq(
+            linear_gradient(&r.into(), -1.0, ColorSpace::Srgb),
+            expected,
+            None,
+        );
 
         let expected = Rgb {
             r: 255,
             g: 255,
             b: 255,
         };
-        assert_rgb_eq(linear_gradient(&r, 100.0), expected, None);
+        assert_rgb_eq(
+            linear_gradient(&r.into(), 100.0, ColorSpace::Srgb),
+            expected,
+            None,
+        );
+    }
+
+    #[test]
+    fn linear_gradient_uses_color_space() {
+        let r = RgbRange {
+            start: Rgb { r: 255, g: 0, b: 0 },
+            end: Rgb { r: 0, g: 0, b: 255 },
+        };
+
+        assert_rgb_eq(
+            linear_gradient(&r.into(), 0.5, ColorSpace::Srgb),
+            Rgb {
+                r: 128,
+                g: 0,
+                b: 128,
+            },
+            None,
+        );
+        assert_rgb_eq(
+            linear_gradient(&r.into(), 0.5, ColorSpace::Hsv),
+            Rgb {
+                r: 255,
+                g: 0,
+                b: 255,
+            },
+            None,
+        );
+    }
+
+    #[test]
+    fn paint_inline_gradient_uses_gradient_color_space() {
+        let msg = "foo bar baz".to_string();
+        let paint = |space: ColorSpace| {
+            let color_format = ColorFormat::InlineGradient(Gradient {
+                space,
+                ..Gradient::new(10)
+            });
+            LogPainter::new(Box::new(theme::Simple {}), Some(color_format)).paint(
+                msg.clone(),
+                Level::Error,
+                ColorSupport::TrueColor,
+            )
+        };
+
+        assert_ne!(paint(ColorSpace::Srgb), paint(ColorSpace::Oklch));
+    }
+
+    #[test]
+    fn linear_gradient_samples_ramp_stops() {
+        let black = Rgb { r: 0, g: 0, b: 0 };
+        let white = Rgb {
+            r: 255,
+            g: 255,
+            b: 255,
+        };
+        let ramp = ColorRamp::new(vec![
+            ColorStop {
+                position: 0.0,
+                color: black,
+            },
+            ColorStop {
+                position: 0.25,
+                color: white,
+            },
+            ColorStop {
+                position: 1.0,
+                color: black,
+            },
+        ]);
+
+        assert_rgb_eq(linear_gradient(&ramp, 0.25, ColorSpace::Srgb), white, None);
+        assert_rgb_eq(
+            linear_gradient(&ramp, 0.625, ColorSpace::Srgb),
+            Rgb {
+                r: 128,
+                g: 128,
+                b: 128,
+            },
+            None,
+        );
+        assert_rgb_eq(linear_gradient(&ramp, -1.0, ColorSpace::Srgb), black, None);
+    }
+
+    #[test]
+    fn paint_multi_line_gradient_uses_theme_ramp() {
+        // a theme whose range is black -> black, with white in the middle of its ramp
+        struct Pulse;
+        impl Theme for Pulse {
+            fn solid(&self, _level: Level) -> Rgb {
+                Rgb::default()
+            }
+            fn range(&self, _level: Level) -> RgbRange {
+                RgbRange::default()
+            }
+            fn ramp(&self, _level: Level) -> ColorRamp {
+                ColorRamp::even(&[
+                    Rgb::default(),
+                    Rgb {
+                        r: 255,
+                        g: 255,
+                        b: 255,
+                    },
+                    Rgb::default(),
+                ])
+            }
+        }
+
+        let color_format = Some(ColorFormat::MultiLineGradient(Gradient::new(2)));
+        let painter = LogPainter::new(Box::new(Pulse), color_format);
+        let lines: Vec<String> = (0..3)
+            .map(|_| painter.paint("foo".to_string(), Level::Info, ColorSupport::TrueColor))
+            .collect();
+
+        assert_eq!(lines[0], "\x1b[38;2;0;0;0mfoo\x1b[0m");
+        assert_eq!(lines[1], "\x1b[38;2;255;255;255mfoo\x1b[0m");
+        assert_eq!(lines[2], "\x1b[38;2;0;0;0mfoo\x1b[0m");
     }
 
     #[test]
@@ -331,17 +985,93 @@ mod tests {
         assert_eq_with_eps(oscillate_dist(638, 255), 0.5, 1e-2);
         assert_eq_with_eps(oscillate_dist(765, 255), 1.0, 1e-2);
         assert_eq_with_eps(
-            oscillate_dist(usize::max_value(), 255),
-            oscillate_dist(usize::max_value() - 255, 255),
+            oscillate_dist(usize::MAX, 255),
+            oscillate_dist(usize::MAX - 255, 255),
             1e-2,
         );
-        assert_eq_with_eps(oscillate_dist(12, usize::max_value()), 1.0, 1e-2);
-        assert_eq_with_eps(oscillate_dist(257, usize::max_value()), 1.0, 1e-2);
-        assert_eq_with_eps(
-            oscillate_dist(usize::max_value(), usize::max_value()),
-            1.0,
-            1e-2,
+        assert_eq_with_eps(oscillate_dist(12, usize::MAX), 1.0, 1e-2);
+        assert_eq_with_eps(oscillate_dist(257, usize::MAX), 1.0, 1e-2);
+        assert_eq_with_eps(oscillate_dist(usize::MAX, usize::MAX), 1.0, 1e-2);
+    }
+
+    #[test]
+    fn wrap_dist_restarts_after_end() {
+        let dists: Vec<f32> = (0..7).map(|x| wrap_dist(x, 2)).collect();
+
+        assert_eq!(dists, vec![0.0, 0.5, 1.0, 0.0, 0.5, 1.0, 0.0]);
+        assert_eq_with_eps(wrap_dist(usize::MAX, usize::MAX), 0.0, 1e-2);
+        assert_eq!(wrap_dist(3, 0), 1.0);
+    }
+
+    #[test]
+    fn once_dist_stays_at_end() {
+        let dists: Vec<f32> = (0..6).map(|x| once_dist(x, 4)).collect();
+
+        assert_eq!(dists, vec![0.0, 0.25, 0.5, 0.75, 1.0, 1.0]);
+        assert_eq!(once_dist(usize::MAX, usize::MAX), 1.0);
+        assert_eq!(once_dist(0, 0), 0.0);
+    }
+
+    #[test]
+    fn easing_maps_known_points() {
+        let easings = [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+            Easing::Sine,
+            Easing::Cubic,
+        ];
+
+        // every curve starts at 0 and ends at 1
+        for easing in easings.iter() {
+            assert_eq_with_eps(easing.apply(0.0), 0.0, 1e-6);
+            assert_eq_with_eps(easing.apply(1.0), 1.0, 1e-6);
+            assert_eq_with_eps(easing.apply(2.0), 1.0, 1e-6);
+        }
+
+        assert_eq_with_eps(Easing::Linear.apply(0.25), 0.25, 1e-6);
+        assert_eq_with_eps(Easing::EaseIn.apply(0.5), 0.25, 1e-6);
+        assert_eq_with_eps(Easing::EaseOut.apply(0.5), 0.75, 1e-6);
+        assert_eq_with_eps(Easing::EaseInOut.apply(0.25), 0.125, 1e-6);
+        assert_eq_with_eps(Easing::EaseInOut.apply(0.5), 0.5, 1e-6);
+        assert_eq_with_eps(Easing::Sine.apply(0.5), 0.5, 1e-6);
+        assert_eq_with_eps(Easing::Sine.apply(0.25), 0.146_446_6, 1e-6);
+        assert_eq_with_eps(Easing::Cubic.apply(0.25), 0.0625, 1e-6);
+        assert_eq_with_eps(Easing::Cubic.apply(0.75), 0.9375, 1e-6);
+    }
+
+    #[test]
+    fn gradient_dist_applies_traversal_and_easing() {
+        let gradient = Gradient {
+            traversal: Traversal::Wrap,
+            easing: Easing::EaseIn,
+            ..Gradient::new(4)
+        };
+        let dists: Vec<f32> = (0..6).map(|x| gradient.dist(x)).collect();
+
+        assert_eq!(dists, vec![0.0, 0.0625, 0.25, 0.5625, 1.0, 0.0]);
+        assert_eq!(Gradient::new(4).dist(6), 0.5);
+    }
+
+    #[test]
+    fn paint_multi_line_gradient_stays_at_end_once() {
+        let gradient = Gradient {
+            traversal: Traversal::Once,
+            ..Gradient::new(2)
+        };
+        let painter = LogPainter::new(
+            Box::new(theme::Simple {}),
+            Some(ColorFormat::MultiLineGradient(gradient)),
         );
+        let lines: Vec<String> = (0..5)
+            .map(|_| painter.paint("foo".to_string(), Level::Info, ColorSupport::TrueColor))
+            .collect();
+
+        assert_ne!(lines[0], lines[1]);
+        assert_ne!(lines[1], lines[2]);
+        assert_eq!(lines[2], lines[3]);
+        assert_eq!(lines[2], lines[4]);
     }
 
     #[test]
@@ -352,13 +1082,17 @@ mod tests {
 
     #[test]
     fn paint_solid_colors_by_level() {
-        assert_logs_colored_by_level(&LogPainter::paint_solid);
+        let color_fn = |painter: &LogPainter, msg: String, level: Level| -> String {
+            painter.paint_solid(msg, level, ColorSupport::TrueColor)
+        };
+
+        assert_logs_colored_by_level(&color_fn);
     }
 
     #[test]
     fn paint_inline_gradient_colors_by_level() {
         let color_fn = |painter: &LogPainter, msg: String, level: Level| -> String {
-            painter.paint_inline_gradient(msg, level, 20)
+            painter.paint_inline_gradient(msg, level, &Gradient::new(20), ColorSupport::TrueColor)
         };
 
         assert_logs_colored_by_level(&color_fn);
@@ -367,7 +1101,12 @@ mod tests {
     #[test]
     fn paint_multi_line_gradient_colors_by_level() {
         let color_fn = |painter: &LogPainter, msg: String, level: Level| -> String {
-            painter.paint_multi_line_gradient(msg, level, 20)
+            painter.paint_multi_line_gradient(
+                msg,
+                level,
+                &Gradient::new(20),
+                ColorSupport::TrueColor,
+            )
         };
 
         assert_logs_colored_by_level(&color_fn);
@@ -380,9 +1119,10 @@ mod tests {
         let painter = LogPainter::new(theme, color_format);
 
         // none of these calls should panic with an empty message
-        painter.paint_solid("".to_string(), Level::Warn);
-        painter.paint_inline_gradient("".to_string(), Level::Warn, 10);
-        painter.paint_multi_line_gradient("".to_string(), Level::Warn, 10);
+        let support = ColorSupport::TrueColor;
+        painter.paint_solid("".to_string(), Level::Warn, support);
+        painter.paint_inline_gradient("".to_string(), Level::Warn, &Gradient::new(10), support);
+        painter.paint_multi_line_gradient("".to_string(), Level::Warn, &Gradient::new(10), support);
     }
 
     #[test]
@@ -393,18 +1133,370 @@ mod tests {
 
         // input msg should not be altered by None color format
         let msg = "foo".to_string();
-        assert_eq!(painter.paint(msg.clone(), Level::Info), msg);
+        assert_eq!(
+            painter.paint(msg.clone(), Level::Info, ColorSupport::TrueColor),
+            msg
+        );
+    }
+
+    #[test]
+    fn paint_applies_theme_level_styles() {
+        struct Loud {}
+
+        impl Theme for Loud {
+            fn solid(&self, _level: Level) -> Rgb {
+                Rgb { r: 1, g: 2, b: 3 }
+            }
+            fn range(&self, level: Level) -> RgbRange {
+                RgbRange {
+                    start: self.solid(level),
+                    end: self.solid(level),
+                }
+            }
+            fn level_style(&self, _level: Level) -> Style {
+                Style {
+                    fg: Some(StyleColor::Rgb(Rgb { r: 9, g: 9, b: 9 })),
+                    bg: Some(StyleColor::Rgb(Rgb { r: 4, g: 5, b: 6 })),
+                    italic: true,
+                    underline: true,
+                    ..Style::default()
+                }
+            }
+        }
+
+        // the level style's foreground color is replaced by the format's colors
+        let painter = LogPainter::new(Box::new(Loud {}), Some(ColorFormat::Solid));
+        assert_eq!(
+            painter.paint("foo".to_string(), Level::Info, ColorSupport::TrueColor),
+            "\x1b[3;4;48;2;4;5;6;38;2;1;2;3mfoo\x1b[0m"
+        );
+
+        let painter = LogPainter::new(
+            Box::new(Loud {}),
+            Some(ColorFormat::InlineGradient(Gradient::new(2))),
+        );
+        assert_eq!(
+            painter.paint("ab".to_string(), Level::Info, ColorSupport::TrueColor),
+            "\x1b[3;4;48;2;4;5;6;38;2;1;2;3ma\x1b[0m\x1b[3;4;48;2;4;5;6;38;2;1;2;3mb\x1b[0m"
+        );
+
+        // lines without a color format keep their text attributes, but no colors
+        let painter = LogPainter::new(Box::new(Loud {}), None);
+        assert_eq!(
+            painter.paint("foo".to_string(), Level::Info, ColorSupport::TrueColor),
+            "\x1b[3;4mfoo\x1b[0m"
+        );
+        assert_eq!(
+            painter.paint("foo".to_string(), Level::Info, ColorSupport::Off),
+            "foo"
+        );
+
+        // warnings and errors are bold by default
+        let painter = LogPainter::new(Box::new(theme::Simple {}), None);
+        assert_eq!(
+            painter.paint("foo".to_string(), Level::Error, ColorSupport::Ansi16),
+            "\x1b[1mfoo\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn paint_downgrades_to_supported_colors() {
+        let theme = Box::new(theme::Simple {});
+        let painter = LogPainter::new(theme, Some(ColorFormat::Solid));
+        let msg = "foo".to_string();
+        let color = theme::Simple {}.solid(Level::Info);
+
+        assert_eq!(
+            painter.paint(msg.clone(), Level::Info, ColorSupport::TrueColor),
+            format!("\x1b[38;2;{};{};{}mfoo\x1b[0m", color.r, color.g, color.b)
+        );
+        assert_eq!(
+            painter.paint(msg.clone(), Level::Info, ColorSupport::Ansi256),
+            format!("\x1b[38;5;{}mfoo\x1b[0m", color.to_ansi256())
+        );
+        assert_eq!(
+            painter.paint(msg.clone(), Level::Info, ColorSupport::Ansi16),
+            "\x1b[32mfoo\x1b[0m"
+        );
+        assert_eq!(
+            painter.paint(msg.clone(), Level::Info, ColorSupport::Off),
+            msg
+        );
+    }
+
+    #[test]
+    fn paint_sculpted_styles_each_segment() {
+        let theme = Box::new(theme::Simple {});
+        let painter = LogPainter::new(theme, Some(ColorFormat::Segmented));
+        let color = theme::Simple {}.solid(Level::Warn);
+        let mut record = SegmentedRecord::new();
+        record.push(Segment::Timestamp, "12:00");
+        record.push(Segment::Text, " ");
+        record.push(Segment::Level, "WARN");
+        record.push(Segment::Text, " - ");
+        record.push(Segment::Message, "foo");
+
+        assert_eq!(
+            painter.paint_sculpted(
+                record.clone(),
+                &log_record(Level::Warn, "app"),
+                ColorSupport::TrueColor
+            ),
+            format!(
+                "\x1b[2m12:00\x1b[0m \x1b[1;38;2;{};{};{}mWARN\x1b[0m - \x1b[1mfoo\x1b[0m",
+                color.r, color.g, color.b
+            )
+        );
+        assert_eq!(
+            painter.paint_sculpted(
+                record.clone(),
+                &log_record(Level::Warn, "app"),
+                ColorSupport::Off
+            ),
+            "12:00 WARN - foo"
+        );
+
+        // other formats paint the whole record
+        let painter = LogPainter::new(Box::new(theme::Simple {}), Some(ColorFormat::Solid));
+        assert_eq!(
+            painter.paint_sculpted(
+                record,
+                &log_record(Level::Warn, "app"),
+                ColorSupport::TrueColor
+            ),
+            format!(
+                "\x1b[1;38;2;{};{};{}m12:00 WARN - foo\x1b[0m",
+                color.r, color.g, color.b
+            )
+        );
+    }
+
+    #[test]
+    fn paint_badged_draws_level_badges() {
+        let painter = LogPainter::new(Box::new(theme::Simple {}), Some(ColorFormat::Badged));
+        let red = theme::Simple {}.solid(Level::Error);
+        let mut record = SegmentedRecord::new();
+        record.push(Segment::Level, "ERROR");
+        record.push(Segment::Text, " - ");
+
+        assert_eq!(
+            painter.paint_sculpted(
+                record.clone(),
+                &log_record(Level::Error, "app"),
+                ColorSupport::TrueColor
+            ),
+            format!(
+                "\x1b[1;48;2;{};{};{};38;2;255;255;255m ERROR \x1b[0m - ",
+                red.r, red.g, red.b
+            )
+        );
+        assert_eq!(
+            painter.paint_sculpted(record, &log_record(Level::Error, "app"), ColorSupport::Off),
+            "ERROR - "
+        );
+
+        // contrasting colors follow a fixed background color
+        let style = Style {
+            bg: Some(StyleColor::Rgb(Rgb {
+                r: 250,
+                g: 250,
+                b: 200,
+            })),
+            ..Style::badge()
+        };
+        assert_eq!(
+            painter.paint_styled("x", &style, Level::Error, ColorSupport::TrueColor),
+            "\x1b[1;48;2;250;250;200;38;2;0;0;0m x \x1b[0m"
+        );
+    }
+
+    #[test]
+    fn stable_hash_matches_fnv1a() {
+        assert_eq!(stable_hash(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(stable_hash("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(stable_hash("foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn paint_hashed_colors_by_target() {
+        struct Pair {}
+
+        impl Theme for Pair {
+            fn solid(&self, level: Level) -> Rgb {
+                theme::Simple {}.solid(level)
+            }
+            fn range(&self, level: Level) -> RgbRange {
+                theme::Simple {}.range(level)
+            }
+            fn palette(&self, _level: Level) -> Vec<Rgb> {
+                vec![Rgb { r: 1, g: 1, b: 1 }, Rgb { r: 2, g: 2, b: 2 }]
+            }
+        }
+
+        let painter = LogPainter::new(
+            Box::new(Pair {}),
+            Some(ColorFormat::Hashed(HashKey::Target)),
+        );
+        let paint = |target: &str| {
+            painter.paint_sculpted(
+                "foo".to_string().into(),
+                &log_record(Level::Info, target),
+                ColorSupport::TrueColor,
+            )
+        };
+
+        // "a" and "b" hash to even and odd palette indices
+        assert_eq!(paint("a"), "\x1b[38;2;1;1;1mfoo\x1b[0m");
+        assert_eq!(paint("b"), "\x1b[38;2;2;2;2mfoo\x1b[0m");
+        assert_eq!(paint("a"), paint("a"));
+
+        // without a palette, colors are picked along the level's ramp
+        let painter = LogPainter::new(
+            Box::new(theme::Spectral {}),
+            Some(ColorFormat::Hashed(HashKey::Target)),
+        );
+        let paint = |target: &str, support: ColorSupport| {
+            painter.paint_sculpted(
+                "foo".to_string().into(),
+                &log_record(Level::Info, target),
+                support,
+            )
+        };
+        assert_eq!(
+            paint("app::db", ColorSupport::TrueColor),
+            paint("app::db", ColorSupport::TrueColor)
+        );
+        assert_ne!(
+            paint("app::db", ColorSupport::TrueColor),
+            paint("app::http", ColorSupport::TrueColor)
+        );
+        assert_eq!(paint("app::db", ColorSupport::Off), "foo");
+    }
+
+    #[test]
+    fn paint_segmented_uses_custom_segment_styles() {
+        struct Quiet {}
+
+        impl Theme for Quiet {
+            fn solid(&self, level: Level) -> Rgb {
+                theme::Simple {}.solid(level)
+            }
+            fn range(&self, level: Level) -> RgbRange {
+                theme::Simple {}.range(level)
+            }
+            fn segment_style(&self, _level: Level, segment: Segment) -> Style {
+                match segment {
+                    Segment::Message => Style {
+                        fg: Some(StyleColor::Rgb(Rgb { r: 1, g: 2, b: 3 })),
+                        ..Style::default()
+                    },
+                    _ => Style::default(),
+                }
+            }
+        }
+
+        let painter = LogPainter::new(Box::new(Quiet {}), Some(ColorFormat::Segmented));
+
+        // unsegmented strings are styled as a message
+        assert_eq!(
+            painter.paint("foo".to_string(), Level::Info, ColorSupport::TrueColor),
+            "\x1b[38;2;1;2;3mfoo\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn paint_inline_gradient_dithers_between_palette_colors() {
+        // a theme whose colors fall between the standard ANSI black and gray
+        struct Gray;
+        impl Theme for Gray {
+            fn solid(&self, _level: Level) -> Rgb {
+                Rgb {
+                    r: 64,
+                    g: 64,
+                    b: 64,
+                }
+            }
+            fn range(&self, level: Level) -> RgbRange {
+                RgbRange {
+                    start: self.solid(level),
+                    end: self.solid(level),
+                }
+            }
+        }
+
+        let mut painter = LogPainter::new(
+            Box::new(Gray),
+            Some(ColorFormat::InlineGradient(Gradient::new(10))),
+        );
+        let msg = "0".repeat(8);
+        let codes = |line: String| {
+            let mut codes = line
+                .split("\x1b[0m")
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+                .collect::<Vec<String>>();
+            codes.sort();
+            codes.dedup();
+            codes.len()
+        };
+
+        // the nearest color is the same for every grapheme
+        assert_eq!(
+            codes(painter.paint(msg.clone(), Level::Info, ColorSupport::Ansi16)),
+            1
+        );
+
+        // dithering mixes the palette colors around the theme's color
+        painter.set_quantization(Quantization::Dither);
+        assert_eq!(
+            codes(painter.paint(msg.clone(), Level::Info, ColorSupport::Ansi16)),
+            2
+        );
+
+        // 24-bit colors are never dithered
+        assert_eq!(
+            codes(painter.paint(msg, Level::Info, ColorSupport::TrueColor)),
+            1
+        );
+    }
+
+    #[test]
+    fn color_code_uses_bright_ansi16_codes() {
+        let white = Rgb {
+            r: 255,
+            g: 255,
+            b: 255,
+        };
+        let red = Rgb { r: 205, g: 0, b: 0 };
+
+        assert_eq!(
+            color_code(white, ColorSupport::Ansi16, false).as_deref(),
+            Some("97")
+        );
+        assert_eq!(
+            color_code(red, ColorSupport::Ansi16, false).as_deref(),
+            Some("31")
+        );
+        assert_eq!(
+            color_code(white, ColorSupport::Ansi16, true).as_deref(),
+            Some("107")
+        );
+        assert_eq!(
+            color_code(red, ColorSupport::Ansi256, true).as_deref(),
+            Some("48;5;160")
+        );
+        assert_eq!(color_code(red, ColorSupport::Off, false), None);
     }
 
     #[test]
     fn paint_log_with_inline_gradient_uses_steps_arg() {
         let theme = Box::new(theme::Simple {});
-        let color_format = Some(ColorFormat::InlineGradient(2));
+        let color_format = Some(ColorFormat::InlineGradient(Gradient::new(2)));
         let painter = LogPainter::new(theme, color_format);
         let msgs = vec!["0000000000".to_string(), "नमस्तेनमस्तेनमस्तेनमस्तेनमस्ते".to_string()];
 
         for msg in msgs {
-            let msg_colored = painter.paint(msg.clone(), Level::Info);
+            let msg_colored = painter.paint(msg.clone(), Level::Info, ColorSupport::TrueColor);
 
             // collect ANSI 24-bit escape sequences to compare color of each grapheme
             let words = msg_colored.unicode_words().collect::<Vec<&str>>();
@@ -431,7 +1523,7 @@ mod tests {
     #[test]
     fn paint_log_with_multi_line_gradient_changes_color_within_level() {
         let theme = Box::new(theme::Simple {});
-        let color_format = Some(ColorFormat::MultiLineGradient(20));
+        let color_format = Some(ColorFormat::MultiLineGradient(Gradient::new(20)));
         let painter = LogPainter::new(theme, color_format);
         let msg = "foo".to_string();
 
@@ -442,7 +1534,7 @@ mod tests {
             let mut last_logged = "".to_string();
 
             for _ in 0..10 {
-                let l = painter.paint(msg.clone(), level);
+                let l = painter.paint(msg.clone(), level, ColorSupport::TrueColor);
                 assert_ne!(last_logged, l);
                 last_logged = l;
             }
@@ -460,23 +1552,23 @@ mod tests {
         // use multi-line gradient with 2 steps in the linear gradient
         let steps: usize = 2;
         let theme = Box::new(theme::Simple {});
-        let color_format = Some(ColorFormat::MultiLineGradient(steps));
+        let color_format = Some(ColorFormat::MultiLineGradient(Gradient::new(steps)));
         let painter = LogPainter::new(theme, color_format);
         let msg = "foo".to_string();
 
-        let lines = vec![
+        let lines = [
             // gradient starts going from start -> end here
-            painter.paint(msg.clone(), Level::Info),
-            painter.paint(msg.clone(), Level::Info),
+            painter.paint(msg.clone(), Level::Info, ColorSupport::TrueColor),
+            painter.paint(msg.clone(), Level::Info, ColorSupport::TrueColor),
             // end -> start
-            painter.paint(msg.clone(), Level::Info),
-            painter.paint(msg.clone(), Level::Info),
+            painter.paint(msg.clone(), Level::Info, ColorSupport::TrueColor),
+            painter.paint(msg.clone(), Level::Info, ColorSupport::TrueColor),
             // gradient should start over here, start -> end
-            painter.paint(msg.clone(), Level::Info),
-            painter.paint(msg.clone(), Level::Info),
+            painter.paint(msg.clone(), Level::Info, ColorSupport::TrueColor),
+            painter.paint(msg.clone(), Level::Info, ColorSupport::TrueColor),
             // end -> start
-            painter.paint(msg.clone(), Level::Info),
-            painter.paint(msg.clone(), Level::Info),
+            painter.paint(msg.clone(), Level::Info, ColorSupport::TrueColor),
+            painter.paint(msg.clone(), Level::Info, ColorSupport::TrueColor),
         ];
 
         // check that gradient restarts at index 4 (2 * steps)
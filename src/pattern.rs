@@ -0,0 +1,588 @@
+//! Record formatting from pattern strings (see `RecordFormat::Pattern`)
+
+use crate::sculpt::{
+    key_values, simple_key_values, thread_name, FormatError, Segment, SegmentedRecord,
+};
+use crate::timestamp::Now;
+use log::Record;
+use time::OffsetDateTime;
+
+/// Alignment of a field that is narrower than its minimum width
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Align {
+    /// pad on the right (`<`)
+    Left,
+    /// pad on the left (`>`)
+    Right,
+    /// pad on both sides (`^`)
+    Center,
+}
+
+/// Width, alignment and truncation of a field (`[[fill]align][width][.max_width]`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Spec {
+    /// character used for padding
+    fill: char,
+    /// alignment within the minimum width
+    align: Align,
+    /// minimum width, in characters
+    width: usize,
+    /// maximum width, in characters (longer fields are truncated)
+    max_width: Option<usize>,
+}
+
+impl Default for Spec {
+    /// Return a `Spec` that leaves fields unchanged
+    fn default() -> Spec {
+        Spec {
+            fill: ' ',
+            align: Align::Left,
+            width: 0,
+            max_width: None,
+        }
+    }
+}
+
+/// Part of a `strftime` style time format
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum TimeItem {
+    /// text copied as-is
+    Literal(String),
+    /// year with century (`%Y`)
+    Year,
+    /// year without century (`%y`)
+    ShortYear,
+    /// month number (`%m`)
+    Month,
+    /// abbreviated month name (`%b`)
+    MonthName,
+    /// day of the month (`%d`)
+    Day,
+    /// day of the year (`%j`)
+    DayOfYear,
+    /// abbreviated weekday name (`%a`)
+    Weekday,
+    /// hour, 24-hour clock (`%H`)
+    Hour,
+    /// minute (`%M`)
+    Minute,
+    /// second (`%S`)
+    Second,
+    /// fraction of a second with this many digits, optionally preceded by a `.`
+    /// (`%f`, `%3f`, `%.3f`, ...)
+    Fraction {
+        /// number of digits
+        digits: u32,
+        /// whether the fraction is preceded by a `.`
+        dot: bool,
+    },
+    /// UTC offset as `+hhmm` (`%z`)
+    Offset,
+    /// seconds since the Unix epoch (`%s`)
+    Timestamp,
+}
+
+/// Value inserted for a placeholder
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Field {
+    /// time the record was logged (the configured timestamp without a format)
+    Time(Option<Vec<TimeItem>>),
+    /// level of the record
+    Level,
+    /// target of the record
+    Target,
+    /// log message
+    Message,
+    /// source file of the record
+    File,
+    /// source line of the record
+    Line,
+    /// module path of the record
+    Module,
+    /// name (or id) of the thread that logged the record
+    Thread,
+    /// structured key-values as `key=value` pairs
+    KeyValues,
+}
+
+/// Part of a compiled pattern
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Piece {
+    /// text copied as-is
+    Literal(String),
+    /// placeholder replaced with a formatted field
+    Field(Field, Spec),
+}
+
+/// Pattern string compiled into literal text and placeholders
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    /// parts of the pattern, in order
+    pieces: Vec<Piece>,
+}
+
+impl Pattern {
+    /// Compile a pattern string
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - the pattern string to compile (see `RecordFormat::Pattern`)
+    pub fn new(pattern: &str) -> Result<Pattern, FormatError> {
+        let mut pieces = vec![];
+        let mut literal = String::new();
+        let mut offset = 0;
+
+        while let Some(pos) = pattern[offset..].find(['{', '}']) {
+            let start = offset + pos;
+            literal.push_str(&pattern[offset..start]);
+            let rest = &pattern[start..];
+
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                literal.push_str(&rest[..1]);
+                offset = start + 2;
+            } else if rest.starts_with('}') {
+                return Err(FormatError::UnmatchedBrace(start));
+            } else {
+                let end = rest
+                    .find('}')
+                    .ok_or(FormatError::UnclosedPlaceholder(start))?;
+
+                if !literal.is_empty() {
+                    pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                }
+
+                pieces.push(parse_placeholder(&rest[1..end])?);
+                offset = start + end + 1;
+            }
+        }
+
+        literal.push_str(&pattern[offset..]);
+
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+
+        Ok(Pattern { pieces })
+    }
+
+    /// Format a log record using this pattern, keeping each placeholder as a separate
+    /// segment (literal text becomes `Text` segments)
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - the log record to format
+    /// * `now` - the time the record was logged
+    pub fn render_segments(&self, record: &Record, now: &Now) -> SegmentedRecord {
+        let mut out = SegmentedRecord::new();
+
+        for piece in self.pieces.iter() {
+            match piece {
+                Piece::Literal(s) => out.push(Segment::Text, s.clone()),
+                Piece::Field(field, spec) => {
+                    let (segment, value) = match field {
+                        Field::Time(None) => (
+                            Segment::Timestamp,
+                            now.stamp.as_ref().map_or(String::new(), |s| s.to_string()),
+                        ),
+                        Field::Time(Some(items)) => {
+                            (Segment::Timestamp, format_time(items, now.time))
+                        }
+                        Field::Level => (Segment::Level, record.level().to_string()),
+                        Field::Target => (Segment::Target, record.target().to_string()),
+                        Field::Message => (Segment::Message, record.args().to_string()),
+                        Field::File => (Segment::Fields, record.file().unwrap_or("?").to_string()),
+                        Field::Line => (
+                            Segment::Fields,
+                            record.line().map_or("?".to_string(), |l| l.to_string()),
+                        ),
+                        Field::Module => (
+                            Segment::Fields,
+                            record.module_path().unwrap_or("?").to_string(),
+                        ),
+                        Field::Thread => (Segment::Fields, thread_name()),
+                        Field::KeyValues => (
+                            Segment::Fields,
+                            simple_key_values(&key_values(record))
+                                .trim_start()
+                                .to_string(),
+                        ),
+                    };
+                    let mut padded = String::new();
+                    pad(&mut padded, &value, spec);
+                    out.push(segment, padded);
+                }
+            }
+        }
+
+        out
+    }
+}
+
+/// Parse the contents of a placeholder (`name` or `name:spec`)
+///
+/// # Arguments
+///
+/// * `placeholder` - text between the placeholder's braces
+fn parse_placeholder(placeholder: &str) -> Result<Piece, FormatError> {
+    let (name, spec) = match placeholder.split_once(':') {
+        Some((name, spec)) => (name, Some(spec)),
+        None => (placeholder, None),
+    };
+
+    let invalid_spec = || FormatError::InvalidSpec {
+        placeholder: name.to_string(),
+        spec: spec.unwrap_or_default().to_string(),
+    };
+
+    // the time placeholder's spec is a time format, rather than a width specifier
+    if name == "time" {
+        let items = match spec {
+            Some(spec) => Some(parse_time_format(spec).ok_or_else(invalid_spec)?),
+            None => None,
+        };
+        return Ok(Piece::Field(Field::Time(items), Spec::default()));
+    }
+
+    let field = match name {
+        "level" => Field::Level,
+        "target" => Field::Target,
+        "message" => Field::Message,
+        "file" => Field::File,
+        "line" => Field::Line,
+        "module" => Field::Module,
+        "thread" => Field::Thread,
+        "kv" => Field::KeyValues,
+        _ => return Err(FormatError::UnknownPlaceholder(name.to_string())),
+    };
+
+    let spec = match spec {
+        Some(spec) => parse_spec(spec).ok_or_else(invalid_spec)?,
+        None => Spec::default(),
+    };
+
+    Ok(Piece::Field(field, spec))
+}
+
+/// Parse a `[[fill]align][width][.max_width]` specifier
+///
+/// # Arguments
+///
+/// * `spec` - the specifier to parse
+fn parse_spec(spec: &str) -> Option<Spec> {
+    let align = |c: char| match c {
+        '<' => Some(Align::Left),
+        '>' => Some(Align::Right),
+        '^' => Some(Align::Center),
+        _ => None,
+    };
+
+    let mut parsed = Spec::default();
+    let chars: Vec<char> = spec.chars().collect();
+    let mut rest = spec;
+
+    if let Some(a) = chars.get(1).and_then(|c| align(*c)) {
+        parsed.fill = chars[0];
+        parsed.align = a;
+        rest = &spec[chars[0].len_utf8() + 1..];
+    } else if let Some(a) = chars.first().and_then(|c| align(*c)) {
+        parsed.align = a;
+        rest = &spec[1..];
+    }
+
+    let (width, max_width) = match rest.split_once('.') {
+        Some((width, max_width)) => (width, Some(max_width)),
+        None => (rest, None),
+    };
+
+    if !width.is_empty() {
+        parsed.width = width.parse().ok()?;
+    }
+
+    if let Some(max_width) = max_width {
+        parsed.max_width = Some(max_width.parse().ok()?);
+    }
+
+    Some(parsed)
+}
+
+/// Parse a `strftime` style time format, e.g. `%H:%M:%S%.3f`
+///
+/// # Arguments
+///
+/// * `format` - the time format to parse
+fn parse_time_format(format: &str) -> Option<Vec<TimeItem>> {
+    let mut items = vec![];
+    let mut literal = String::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            literal.push(c);
+            continue;
+        }
+
+        let dot = chars.next_if_eq(&'.').is_some();
+        let digits = chars.next_if(|c| matches!(c, '3' | '6' | '9'));
+
+        let item = match (chars.next()?, dot, digits) {
+            ('f', _, digits) => TimeItem::Fraction {
+                digits: digits.map_or(9, |d| d.to_digit(10).unwrap()),
+                dot,
+            },
+            (_, true, _) | (_, _, Some(_)) => return None,
+            ('%', ..) => {
+                literal.push('%');
+                continue;
+            }
+            ('Y', ..) => TimeItem::Year,
+            ('y', ..) => TimeItem::ShortYear,
+            ('m', ..) => TimeItem::Month,
+            ('b', ..) => TimeItem::MonthName,
+            ('d', ..) => TimeItem::Day,
+            ('j', ..) => TimeItem::DayOfYear,
+            ('a', ..) => TimeItem::Weekday,
+            ('H', ..) => TimeItem::Hour,
+            ('M', ..) => TimeItem::Minute,
+            ('S', ..) => TimeItem::Second,
+            ('z', ..) => TimeItem::Offset,
+            ('s', ..) => TimeItem::Timestamp,
+            ('T', ..) => {
+                items.extend(take_literal(&mut literal));
+                items.extend([
+                    TimeItem::Hour,
+                    TimeItem::Literal(":".to_string()),
+                    TimeItem::Minute,
+                    TimeItem::Literal(":".to_string()),
+                    TimeItem::Second,
+                ]);
+                continue;
+            }
+            ('F', ..) => {
+                items.extend(take_literal(&mut literal));
+                items.extend([
+                    TimeItem::Year,
+                    TimeItem::Literal("-".to_string()),
+                    TimeItem::Month,
+                    TimeItem::Literal("-".to_string()),
+                    TimeItem::Day,
+                ]);
+                continue;
+            }
+            _ => return None,
+        };
+
+        items.extend(take_literal(&mut literal));
+        items.push(item);
+    }
+
+    items.extend(take_literal(&mut literal));
+    Some(items)
+}
+
+/// Take the pending literal text of a time format as an item, if there is any
+///
+/// # Arguments
+///
+/// * `literal` - pending literal text (left empty)
+fn take_literal(literal: &mut String) -> Option<TimeItem> {
+    if literal.is_empty() {
+        None
+    } else {
+        Some(TimeItem::Literal(std::mem::take(literal)))
+    }
+}
+
+/// Format a time using a parsed time format
+///
+/// # Arguments
+///
+/// * `items` - the parsed time format
+/// * `time` - the time to format
+fn format_time(items: &[TimeItem], time: OffsetDateTime) -> String {
+    let mut out = String::new();
+
+    for item in items.iter() {
+        let s = match item {
+            TimeItem::Literal(s) => s.clone(),
+            TimeItem::Year => format!("{:04}", time.year()),
+            TimeItem::ShortYear => format!("{:02}", time.year().rem_euclid(100)),
+            TimeItem::Month => format!("{:02}", time.month() as u8),
+            TimeItem::MonthName => time.month().to_string()[..3].to_string(),
+            TimeItem::Day => format!("{:02}", time.day()),
+            TimeItem::DayOfYear => format!("{:03}", time.ordinal()),
+            TimeItem::Weekday => time.weekday().to_string()[..3].to_string(),
+            TimeItem::Hour => format!("{:02}", time.hour()),
+            TimeItem::Minute => format!("{:02}", time.minute()),
+            TimeItem::Second => format!("{:02}", time.second()),
+            TimeItem::Fraction { digits, dot } => format!(
+                "{}{:0width$}",
+                if *dot { "." } else { "" },
+                time.nanosecond() / 10u32.pow(9 - digits),
+                width = *digits as usize
+            ),
+            TimeItem::Offset => {
+                let offset = time.offset();
+                format!(
+                    "{}{:02}{:02}",
+                    if offset.is_negative() { '-' } else { '+' },
+                    offset.whole_hours().abs(),
+                    offset.minutes_past_hour().abs()
+                )
+            }
+            TimeItem::Timestamp => time.unix_timestamp().to_string(),
+        };
+        out.push_str(&s);
+    }
+
+    out
+}
+
+/// Append a field to `out`, truncated and padded according to `spec`
+///
+/// # Arguments
+///
+/// * `out` - string to append to
+/// * `value` - the field to append
+/// * `spec` - width, alignment and truncation of the field
+fn pad(out: &mut String, value: &str, spec: &Spec) {
+    let value = match spec.max_width {
+        Some(max) => value.chars().take(max).collect(),
+        None => value.to_string(),
+    };
+
+    let padding = spec.width.saturating_sub(value.chars().count());
+    let (left, right) = match spec.align {
+        Align::Left => (0, padding),
+        Align::Right => (padding, 0),
+        Align::Center => (padding / 2, padding - padding / 2),
+    };
+
+    out.extend(std::iter::repeat_n(spec.fill, left));
+    out.push_str(&value);
+    out.extend(std::iter::repeat_n(spec.fill, right));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestamp::Stamp;
+    use log::Level;
+    use time::UtcOffset;
+
+    // helpers
+
+    /// Render a record with message `foo` at a fixed time using `pattern`
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - the pattern string to render
+    fn render(pattern: &str) -> String {
+        render_segments(pattern).into_text()
+    }
+
+    /// Render a record with message `foo` at a fixed time using `pattern`, keeping its
+    /// segments
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - the pattern string to render
+    fn render_segments(pattern: &str) -> SegmentedRecord {
+        let rec = Record::builder()
+            .args(format_args!("foo"))
+            .level(Level::Warn)
+            .target("app::db")
+            .file(Some("src/db.rs"))
+            .line(Some(42))
+            .module_path(Some("app::db"))
+            .build();
+
+        let now = Now {
+            time: OffsetDateTime::from_unix_timestamp_nanos(1_659_291_931_108_560_826)
+                .unwrap()
+                .to_offset(UtcOffset::from_hms(2, 0, 0).unwrap()),
+            stamp: Some(Stamp::Text(
+                "2022-07-31T20:25:31.108560826+02:00".to_string(),
+            )),
+        };
+
+        Pattern::new(pattern).unwrap().render_segments(&rec, &now)
+    }
+
+    // tests
+
+    #[test]
+    fn render_substitutes_placeholders() {
+        assert_eq!(
+            render("{time:%H:%M:%S%.3f} {level:>5} [{target}] {file}:{line} - {message}"),
+            "20:25:31.108  WARN [app::db] src/db.rs:42 - foo"
+        );
+        assert_eq!(render("{module}"), "app::db");
+        assert_eq!(render("{time}"), "2022-07-31T20:25:31.108560826+02:00");
+        assert_eq!(
+            render("{time:%F %T %z %a %b %j %y %6f %%}"),
+            "2022-07-31 20:25:31 +0200 Sun Jul 212 22 108560 %"
+        );
+    }
+
+    #[test]
+    fn render_applies_width_alignment_and_truncation() {
+        assert_eq!(render("[{level:<6}]"), "[WARN  ]");
+        assert_eq!(render("[{level:^8}]"), "[  WARN  ]");
+        assert_eq!(render("[{level:*>6}]"), "[**WARN]");
+        assert_eq!(render("[{target:.3}]"), "[app]");
+        assert_eq!(render("[{target:>5.2}]"), "[   ap]");
+        assert_eq!(render("[{message:2}]"), "[foo]");
+    }
+
+    #[test]
+    fn render_handles_escaped_braces() {
+        assert_eq!(render("{{{message}}}"), "{foo}");
+        assert_eq!(render("no placeholders"), "no placeholders");
+        assert_eq!(render(""), "");
+    }
+
+    #[test]
+    fn render_segments_splits_placeholders() {
+        assert_eq!(
+            render_segments("{time:%H:%M} {level:>5} [{target}] {line} - {message}").segments(),
+            &[
+                (Segment::Timestamp, "20:25".to_string()),
+                (Segment::Text, " ".to_string()),
+                (Segment::Level, " WARN".to_string()),
+                (Segment::Text, " [".to_string()),
+                (Segment::Target, "app::db".to_string()),
+                (Segment::Text, "] ".to_string()),
+                (Segment::Fields, "42".to_string()),
+                (Segment::Text, " - ".to_string()),
+                (Segment::Message, "foo".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn new_rejects_invalid_patterns() {
+        assert_eq!(
+            Pattern::new("{level} {bogus}"),
+            Err(FormatError::UnknownPlaceholder("bogus".to_string()))
+        );
+        assert_eq!(
+            Pattern::new("{level"),
+            Err(FormatError::UnclosedPlaceholder(0))
+        );
+        assert_eq!(Pattern::new("level}"), Err(FormatError::UnmatchedBrace(5)));
+        assert_eq!(
+            Pattern::new("{level:>x}"),
+            Err(FormatError::InvalidSpec {
+                placeholder: "level".to_string(),
+                spec: ">x".to_string()
+            })
+        );
+        assert_eq!(
+            Pattern::new("{time:%Q}"),
+            Err(FormatError::InvalidSpec {
+                placeholder: "time".to_string(),
+                spec: "%Q".to_string()
+            })
+        );
+    }
+}
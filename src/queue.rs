@@ -0,0 +1,556 @@
+//! Asynchronous writing of log records on a background thread
+
+use crate::write::{LogWriter, Payload};
+use log::Level;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread::{self, JoinHandle, ThreadId};
+
+/// Name of the background thread that writes queued records
+pub const WRITER_THREAD_NAME: &str = "swing-writer";
+
+/// Behavior when a record is logged while the async queue is full
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// block the logging thread until there is room in the queue
+    Block,
+    /// drop the record being logged
+    DropNewest,
+    /// drop the oldest record in the queue to make room for the record being logged
+    DropOldest,
+}
+
+/// Configuration for writing log records asynchronously
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AsyncConfig {
+    /// maximum number of records waiting to be written
+    pub capacity: usize,
+    /// behavior when a record is logged while the queue is full
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for AsyncConfig {
+    /// Return an `AsyncConfig` with default values
+    fn default() -> AsyncConfig {
+        AsyncConfig {
+            capacity: 1024,
+            overflow: OverflowPolicy::Block,
+        }
+    }
+}
+
+/// Formatted record waiting to be written
+struct Job {
+    /// writer for the record's sink
+    writer: Arc<LogWriter>,
+    /// formatted log message
+    msg: Payload,
+    /// level of the log message
+    level: Level,
+}
+
+/// Queue contents, guarded by a mutex
+struct QueueState {
+    /// records waiting to be written
+    jobs: VecDeque<Job>,
+    /// whether the writer thread is currently writing a record taken from the queue
+    busy: bool,
+    /// whether the queue is shutting down
+    closed: bool,
+}
+
+/// Queue shared between logging threads and the writer thread
+struct Shared {
+    /// queue contents
+    state: Mutex<QueueState>,
+    /// signaled when records are added to the queue or it is closed
+    not_empty: Condvar,
+    /// signaled when records are taken from the queue or it is closed
+    not_full: Condvar,
+    /// signaled when the queue is empty and all records have been written
+    idle: Condvar,
+    /// number of records dropped because the queue was full
+    dropped: AtomicU64,
+    /// queue configuration
+    config: AsyncConfig,
+}
+
+impl Shared {
+    /// Create a new, empty Shared queue
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - queue configuration
+    fn new(config: AsyncConfig) -> Shared {
+        Shared {
+            state: Mutex::new(QueueState {
+                jobs: VecDeque::new(),
+                busy: false,
+                closed: false,
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            idle: Condvar::new(),
+            dropped: AtomicU64::new(0),
+            config,
+        }
+    }
+}
+
+/// Add a job to the queue according to the overflow policy, without blocking
+///
+/// Returns the job if it couldn't be added because the queue is full and the
+/// policy is `Block`, so the caller can wait for room and try again
+///
+/// # Arguments
+///
+/// * `config` - queue configuration
+/// * `dropped` - counter of dropped records
+/// * `jobs` - queued jobs
+/// * `job` - the job to add
+fn try_enqueue(
+    config: &AsyncConfig,
+    dropped: &AtomicU64,
+    jobs: &mut VecDeque<Job>,
+    job: Job,
+) -> Option<Job> {
+    if jobs.len() < config.capacity.max(1) {
+        jobs.push_back(job);
+        return None;
+    }
+
+    match config.overflow {
+        OverflowPolicy::Block => return Some(job),
+        OverflowPolicy::DropNewest => {}
+        OverflowPolicy::DropOldest => {
+            jobs.pop_front();
+            jobs.push_back(job);
+        }
+    }
+
+    dropped.fetch_add(1, Ordering::Relaxed);
+    None
+}
+
+/// Bounded queue of formatted records, drained by a dedicated writer thread
+pub struct WriteQueue {
+    /// queue shared with the writer thread
+    shared: Arc<Shared>,
+    /// handle to the writer thread
+    thread: Option<JoinHandle<()>>,
+    /// id of the writer thread
+    writer_id: ThreadId,
+}
+
+impl WriteQueue {
+    /// Create a new WriteQueue and start its writer thread
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - queue configuration
+    pub fn new(config: AsyncConfig) -> WriteQueue {
+        let shared = Arc::new(Shared::new(config));
+
+        let thread = {
+            let shared = Arc::clone(&shared);
+            thread::Builder::new()
+                .name(WRITER_THREAD_NAME.to_string())
+                .spawn(move || drain(&shared))
+                .expect("Failed to spawn log writer thread")
+        };
+
+        WriteQueue {
+            shared,
+            writer_id: thread.thread().id(),
+            thread: Some(thread),
+        }
+    }
+
+    /// Queue a formatted record to be written by the writer thread
+    ///
+    /// The record is dropped if the writer thread has stopped
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - writer for the record's sink
+    /// * `msg` - the log message to write
+    /// * `level` - the level of this log message
+    pub fn push(&self, writer: Arc<LogWriter>, msg: Payload, level: Level) {
+        let mut job = Job { writer, msg, level };
+        let mut state = self.shared.state.lock().unwrap();
+
+        loop {
+            if state.closed {
+                self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+
+            match try_enqueue(
+                &self.shared.config,
+                &self.shared.dropped,
+                &mut state.jobs,
+                job,
+            ) {
+                Some(j) => job = j,
+                None => break,
+            }
+
+            state = self.shared.not_full.wait(state).unwrap();
+        }
+
+        self.shared.not_empty.notify_one();
+    }
+
+    /// Block until every queued record has been written
+    ///
+    /// Returns immediately when called from this queue's writer thread, which would
+    /// otherwise wait for itself
+    pub fn flush(&self) {
+        if thread::current().id() == self.writer_id {
+            return;
+        }
+
+        let mut state = self.shared.state.lock().unwrap();
+
+        while !state.jobs.is_empty() || state.busy {
+            state = self.shared.idle.wait(state).unwrap();
+        }
+    }
+
+    /// Get the number of records dropped because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for WriteQueue {
+    /// Write all queued records, then stop the writer thread
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().closed = true;
+        self.shared.not_empty.notify_one();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Marks the writer thread busy while it writes a record taken from the queue
+///
+/// Dropping the guard marks the writer idle again.  If the writer is unwinding from a
+/// panic, the queue is also closed and its remaining records dropped, so that `flush`
+/// and blocked logging threads don't wait for a writer that is gone
+struct Busy<'a> {
+    /// queue the record was taken from
+    shared: &'a Shared,
+}
+
+impl<'a> Busy<'a> {
+    /// Mark the writer busy and create a guard that marks it idle when dropped
+    ///
+    /// # Arguments
+    ///
+    /// * `shared` - queue the record was taken from
+    /// * `state` - the queue's locked contents
+    fn new(shared: &'a Shared, state: &mut QueueState) -> Busy<'a> {
+        state.busy = true;
+        Busy { shared }
+    }
+}
+
+impl Drop for Busy<'_> {
+    /// Mark the writer idle, closing the queue if the writer panicked
+    fn drop(&mut self) {
+        let mut state = self
+            .shared
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        state.busy = false;
+
+        if thread::panicking() {
+            state.closed = true;
+            self.shared
+                .dropped
+                .fetch_add(state.jobs.len() as u64, Ordering::Relaxed);
+            state.jobs.clear();
+            self.shared.not_full.notify_all();
+        }
+
+        if state.jobs.is_empty() {
+            self.shared.idle.notify_all();
+        }
+    }
+}
+
+/// Write queued records one at a time until the queue is closed and empty
+///
+/// # Arguments
+///
+/// * `shared` - queue to drain
+fn drain(shared: &Shared) {
+    loop {
+        let (job, _busy) = {
+            let mut state = shared.state.lock().unwrap();
+
+            while state.jobs.is_empty() && !state.closed {
+                state = shared.not_empty.wait(state).unwrap();
+            }
+
+            match state.jobs.pop_front() {
+                Some(job) => (job, Busy::new(shared, &mut state)),
+                None => return,
+            }
+        };
+        shared.not_full.notify_one();
+
+        job.writer.write(job.msg, job.level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::FileConfig;
+    use crate::testing::TempPath;
+    use crate::write::Destination;
+    use std::fs;
+
+    // helpers
+
+    /// Create a job with message `msg`
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - message for the job
+    fn job(msg: &str) -> Job {
+        Job {
+            writer: Arc::new(LogWriter::new(Destination::Console, false, None)),
+            msg: Payload::Text(msg.to_string()),
+            level: Level::Info,
+        }
+    }
+
+    /// Get the messages of all queued jobs
+    ///
+    /// # Arguments
+    ///
+    /// * `jobs` - queued jobs
+    fn msgs(jobs: &VecDeque<Job>) -> Vec<Payload> {
+        jobs.iter().map(|j| j.msg.clone()).collect()
+    }
+
+    /// Create text payloads from messages
+    ///
+    /// # Arguments
+    ///
+    /// * `msgs` - the messages
+    fn text(msgs: &[&str]) -> Vec<Payload> {
+        msgs.iter().map(|m| Payload::Text(m.to_string())).collect()
+    }
+
+    /// Queue a job for each message in `msgs`, asserting that none are returned
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - queue configuration
+    /// * `msgs` - messages to queue
+    fn enqueue_all(config: &AsyncConfig, msgs: &[&str]) -> (VecDeque<Job>, u64) {
+        let dropped = AtomicU64::new(0);
+        let mut jobs = VecDeque::new();
+
+        for msg in msgs {
+            assert!(try_enqueue(config, &dropped, &mut jobs, job(msg)).is_none());
+        }
+
+        (jobs, dropped.load(Ordering::Relaxed))
+    }
+
+    // tests
+
+    #[test]
+    fn try_enqueue_drop_newest_keeps_queue() {
+        let config = AsyncConfig {
+            capacity: 2,
+            overflow: OverflowPolicy::DropNewest,
+        };
+        let (jobs, dropped) = enqueue_all(&config, &["a", "b", "c", "d"]);
+
+        assert_eq!(msgs(&jobs), text(&["a", "b"]));
+        assert_eq!(dropped, 2);
+    }
+
+    #[test]
+    fn try_enqueue_drop_oldest_keeps_newest() {
+        let config = AsyncConfig {
+            capacity: 2,
+            overflow: OverflowPolicy::DropOldest,
+        };
+        let (jobs, dropped) = enqueue_all(&config, &["a", "b", "c", "d"]);
+
+        assert_eq!(msgs(&jobs), text(&["c", "d"]));
+        assert_eq!(dropped, 2);
+    }
+
+    #[test]
+    fn try_enqueue_block_returns_job_when_full() {
+        let config = AsyncConfig {
+            capacity: 0,
+            overflow: OverflowPolicy::Block,
+        };
+        let dropped = AtomicU64::new(0);
+        let mut jobs = VecDeque::new();
+
+        // a capacity of 0 still allows one queued record
+        assert!(try_enqueue(&config, &dropped, &mut jobs, job("a")).is_none());
+        assert_eq!(
+            try_enqueue(&config, &dropped, &mut jobs, job("b"))
+                .unwrap()
+                .msg,
+            Payload::Text("b".to_string())
+        );
+        assert_eq!(msgs(&jobs), text(&["a"]));
+        assert_eq!(dropped.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn flush_waits_for_all_records() {
+        let path = TempPath::new("queue-flush.log");
+
+        let writer = Arc::new(LogWriter::new(
+            Destination::File(FileConfig::new(&*path)),
+            false,
+            None,
+        ));
+        let queue = WriteQueue::new(AsyncConfig {
+            capacity: 4,
+            overflow: OverflowPolicy::Block,
+        });
+
+        for i in 0..100 {
+            queue.push(
+                Arc::clone(&writer),
+                Payload::Text(i.to_string()),
+                Level::Info,
+            );
+        }
+        queue.flush();
+
+        let expected = (0..100).map(|i| format!("{}\n", i)).collect::<String>();
+        assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+        assert_eq!(queue.dropped(), 0);
+    }
+
+    #[test]
+    fn drop_writes_queued_records() {
+        let path = TempPath::new("queue-drop.log");
+
+        let writer = Arc::new(LogWriter::new(
+            Destination::File(FileConfig::new(&*path)),
+            false,
+            None,
+        ));
+        let queue = WriteQueue::new(AsyncConfig::default());
+
+        for i in 0..10 {
+            queue.push(
+                Arc::clone(&writer),
+                Payload::Text(i.to_string()),
+                Level::Info,
+            );
+        }
+        drop(queue);
+
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 10);
+    }
+
+    #[test]
+    fn flush_waits_on_threads_named_like_the_writer() {
+        let path = TempPath::new("queue-name.log");
+
+        let writer = Arc::new(LogWriter::new(
+            Destination::File(FileConfig::new(&*path)),
+            false,
+            None,
+        ));
+        let queue = WriteQueue::new(AsyncConfig {
+            capacity: 4,
+            overflow: OverflowPolicy::Block,
+        });
+
+        // only the queue's own writer thread skips waiting, not every thread with its name
+        thread::scope(|scope| {
+            thread::Builder::new()
+                .name(WRITER_THREAD_NAME.to_string())
+                .spawn_scoped(scope, || {
+                    for i in 0..100 {
+                        queue.push(
+                            Arc::clone(&writer),
+                            Payload::Text(i.to_string()),
+                            Level::Info,
+                        );
+                    }
+                    queue.flush();
+
+                    assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 100);
+                })
+                .unwrap();
+        });
+    }
+
+    #[test]
+    fn busy_closes_queue_when_writer_panics() {
+        let shared = Shared::new(AsyncConfig::default());
+        shared
+            .state
+            .lock()
+            .unwrap()
+            .jobs
+            .extend(vec![job("a"), job("b")]);
+
+        let result = thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let mut state = shared.state.lock().unwrap();
+                    let _busy = Busy::new(&shared, &mut state);
+                    drop(state);
+
+                    panic!("writer panicked");
+                })
+                .join()
+        });
+        assert!(result.is_err());
+
+        let state = shared.state.lock().unwrap();
+        assert!(!state.busy);
+        assert!(state.closed);
+        assert!(state.jobs.is_empty());
+        assert_eq!(shared.dropped.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn push_drops_records_after_writer_stops() {
+        let queue = WriteQueue::new(AsyncConfig {
+            capacity: 1,
+            overflow: OverflowPolicy::Block,
+        });
+        queue.shared.state.lock().unwrap().closed = true;
+        queue.shared.not_empty.notify_one();
+
+        // neither call waits for the stopped writer thread
+        for i in 0..3 {
+            queue.push(
+                Arc::new(LogWriter::new(Destination::Console, false, None)),
+                Payload::Text(i.to_string()),
+                Level::Info,
+            );
+        }
+        queue.flush();
+
+        assert_eq!(queue.dropped(), 3);
+    }
+}
@@ -1,22 +1,322 @@
+use crate::pattern::Pattern;
+use crate::syslog::{Protocol, SyslogConfig, SyslogFormatter};
+use crate::timestamp::{Clock, Now};
+use log::kv::{self, Key, VisitSource};
 use log::Record;
-use serde_json::json;
-use time::format_description::well_known::Iso8601;
-use time::OffsetDateTime;
+use serde::Deserialize;
+use serde_json::{json, Map, Number, Value};
+use std::fmt;
+use std::thread;
 
 /// Record formatting mode that determines how log records are structured
 pub enum RecordFormat {
     /// JSON format
     Json,
-    /// simple log format `<timestamp> [<target>] - <message>`
+    /// simple log format `<timestamp> [<target>] <level> - <message> <key>=<value>...`, with
+    /// enabled `RecordFields` after the level (`(<thread>) <module> <file>:<line>`)
     Simple,
+    /// logfmt format `time=<timestamp> level=<level> target=<target> msg=<message>
+    /// <key>=<value>...`, with enabled `RecordFields` before the message (`file`, `line`,
+    /// `module`, `thread` keys)
+    Logfmt,
     /// custom record formatter provided by client code
     Custom(Box<dyn Sync + Send + Fn(&Record) -> String>),
+    /// format described by a pattern string, e.g.
+    /// `{time:%H:%M:%S%.3f} {level:>5} [{target}] {file}:{line} - {message}`
+    ///
+    /// Placeholders are `time`, `level`, `target`, `message`, `file`, `line`, `module`,
+    /// `thread` and `kv` (structured key-values as `key=value` pairs).  Each one except
+    /// `time` takes an optional `[[fill]align][width][.max_width]` specifier, where `align`
+    /// is `<`, `>` or `^`.  The `time` placeholder instead takes a `strftime` style format
+    /// (`%Y %y %m %b %d %j %a %H %M %S %f %.3f %.6f %.9f %z %s %T %F %%`) in the configured
+    /// offset, and defaults to the configured timestamp (see `Config::timestamp`).  Literal
+    /// braces are written as `{{` and `}}`.
+    ///
+    /// The pattern is compiled when the record format is used to create a logger or sink
+    Pattern(String),
+    /// RFC 5424 syslog message `<PRI>1 <timestamp> <hostname> <app-name> <procid> - -
+    /// <message> <key>=<value>...`
+    ///
+    /// Timestamps use the configured offset and microsecond precision (see `Config::timestamp`)
+    Rfc5424(SyslogConfig),
+    /// RFC 3164 (BSD) syslog message `<PRI>Mmm dd hh:mm:ss <hostname> <app-name>[<procid>]:
+    /// <message> <key>=<value>...`
+    ///
+    /// Timestamps use the configured offset, and are never omitted
+    Rfc3164(SyslogConfig),
+}
+
+/// Error returned when a record format is invalid
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FormatError {
+    /// a pattern contains a placeholder that isn't supported
+    UnknownPlaceholder(String),
+    /// a pattern contains a `{` without a matching `}` (at this byte offset)
+    UnclosedPlaceholder(usize),
+    /// a pattern contains a `}` without a matching `{` (at this byte offset)
+    UnmatchedBrace(usize),
+    /// a placeholder in a pattern has an invalid specifier
+    InvalidSpec {
+        /// name of the placeholder
+        placeholder: String,
+        /// the invalid specifier
+        spec: String,
+    },
+    /// a custom timestamp format is not a valid `time` format description
+    InvalidTimeFormat {
+        /// the invalid format description
+        format: String,
+        /// description of why the format description is invalid
+        reason: String,
+    },
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FormatError::UnknownPlaceholder(name) => write!(
+                f,
+                "unknown placeholder \"{{{}}}\" in record pattern (expected one of time, level, target, message, file, line, module, thread, kv)",
+                name
+            ),
+            FormatError::UnclosedPlaceholder(offset) => {
+                write!(f, "unclosed placeholder at byte {} of record pattern", offset)
+            }
+            FormatError::UnmatchedBrace(offset) => write!(
+                f,
+                "unmatched \"}}\" at byte {} of record pattern (write \"}}}}\" for a literal brace)",
+                offset
+            ),
+            FormatError::InvalidSpec { placeholder, spec } => write!(
+                f,
+                "invalid specifier \"{}\" for placeholder \"{{{}}}\" in record pattern",
+                spec, placeholder
+            ),
+            FormatError::InvalidTimeFormat { format, reason } => {
+                write!(f, "invalid timestamp format \"{}\": {}", format, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Optional record fields included by the built-in `Json` and `Simple` record formats
+///
+/// Fields that a record doesn't have (e.g. the file of a record created without
+/// the `log` macros) are left out
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecordFields {
+    /// include the source file that logged the record (`file` key in `Json`)
+    pub file: bool,
+    /// include the source line that logged the record (`line` key in `Json`)
+    pub line: bool,
+    /// include the module path that logged the record (`module` key in `Json`)
+    pub module: bool,
+    /// include the name (or id, if unnamed) of the thread that logged the record
+    /// (`thread` key in `Json`)
+    pub thread: bool,
+}
+
+/// Settings for the built-in record formats, shared by every sink of a logger
+#[derive(Clone, Debug, Default)]
+pub struct SculptOptions {
+    /// key to nest structured key-values under in `Json` records (`None` adds them
+    /// as top-level fields)
+    pub json_kv_key: Option<String>,
+    /// source of record timestamps
+    pub clock: Clock,
+    /// optional record fields to include
+    pub fields: RecordFields,
+}
+
+/// Part of a sculpted record that can be styled independently by the painter
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Segment {
+    /// time the record was logged
+    Timestamp,
+    /// level of the record
+    Level,
+    /// target of the record
+    Target,
+    /// log message
+    Message,
+    /// optional record fields (file, line, module, thread) and structured key-values
+    Fields,
+    /// separators and other literal text
+    Text,
+}
+
+/// Sculpted record split into segments, in output order
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SegmentedRecord {
+    /// segments of the record and their text
+    segments: Vec<(Segment, String)>,
+}
+
+impl SegmentedRecord {
+    /// Create an empty SegmentedRecord
+    pub fn new() -> SegmentedRecord {
+        SegmentedRecord::default()
+    }
+
+    /// Append a segment, skipping it if its text is empty
+    ///
+    /// # Arguments
+    ///
+    /// * `segment` - which part of the record the text is
+    /// * `text` - text of the segment
+    pub fn push(&mut self, segment: Segment, text: impl Into<String>) {
+        let text = text.into();
+
+        if !text.is_empty() {
+            self.segments.push((segment, text));
+        }
+    }
+
+    /// Get the segments of the record, in output order
+    pub fn segments(&self) -> &[(Segment, String)] {
+        &self.segments
+    }
+
+    /// Join the segments into the record's text
+    pub fn into_text(self) -> String {
+        self.segments.into_iter().map(|(_, text)| text).collect()
+    }
+}
+
+impl From<String> for SegmentedRecord {
+    /// Create a SegmentedRecord with `text` as its only (`Message`) segment, for record
+    /// formats that aren't split into segments
+    fn from(text: String) -> SegmentedRecord {
+        let mut record = SegmentedRecord::new();
+        record.push(Segment::Message, text);
+        record
+    }
+}
+
+/// Visitor that collects the structured key-values of a record
+struct KeyValues(Vec<(String, Value)>);
+
+impl<'kvs> VisitSource<'kvs> for KeyValues {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.push((key.as_str().to_string(), typed_value(&value)));
+        Ok(())
+    }
+}
+
+/// Convert a structured value into a JSON value, keeping its type when it is
+/// a bool or a number, and converting it to a string otherwise
+///
+/// # Arguments
+///
+/// * `value` - the value to convert
+fn typed_value(value: &kv::Value) -> Value {
+    if let Some(b) = value.to_bool() {
+        Value::Bool(b)
+    } else if let Some(n) = value.to_i64() {
+        Value::from(n)
+    } else if let Some(n) = value.to_u64() {
+        Value::from(n)
+    } else if let Some(n) = value.to_f64().and_then(Number::from_f64) {
+        Value::Number(n)
+    } else {
+        Value::String(value.to_string())
+    }
+}
+
+/// Get the structured key-values of a record, in the order they were logged
+///
+/// # Arguments
+///
+/// * `record` - the record to get key-values from
+pub(crate) fn key_values(record: &Record) -> Vec<(String, Value)> {
+    let mut kvs = KeyValues(vec![]);
+    let _ = record.key_values().visit(&mut kvs);
+    kvs.0
+}
+
+/// Get the name of the current thread, or its id if it is unnamed
+pub(crate) fn thread_name() -> String {
+    let current = thread::current();
+    match current.name() {
+        Some(name) => name.to_string(),
+        None => format!("{:?}", current.id()),
+    }
+}
+
+/// Format structured key-values as ` <key>=<value>` pairs, quoting string values
+///
+/// # Arguments
+///
+/// * `kvs` - the key-values to format
+pub(crate) fn simple_key_values(kvs: &[(String, Value)]) -> String {
+    kvs.iter().map(|(k, v)| format!(" {}={}", k, v)).collect()
+}
+
+/// Format a logfmt key, replacing characters that aren't allowed in keys with `_`
+///
+/// # Arguments
+///
+/// * `key` - the key to format
+fn logfmt_key(key: &str) -> String {
+    key.chars()
+        .map(|c| {
+            if c == '=' || c == '"' || c.is_whitespace() || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Format a logfmt value, quoting and escaping it if it is empty or contains spaces,
+/// quotes, `=`, or control characters
+///
+/// # Arguments
+///
+/// * `value` - the value to format
+fn logfmt_value(value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value
+            .chars()
+            .any(|c| c == '=' || c == '"' || c == '\\' || c.is_whitespace() || c.is_control());
+
+    if !needs_quotes {
+        return value.to_string();
+    }
+
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
 }
 
 /// Sculpt/create structurally formatted string logs from raw log records
 pub struct LogSculptor {
     /// record formatting mode (determines how log records are structurally formatted)
-    pub record_format: RecordFormat,
+    record_format: RecordFormat,
+    /// compiled pattern, if `record_format` is `Pattern`
+    pattern: Option<Pattern>,
+    /// syslog formatter, if `record_format` is `Rfc5424` or `Rfc3164`
+    syslog: Option<SyslogFormatter>,
+    /// settings for the built-in record formats
+    pub options: SculptOptions,
 }
 
 impl LogSculptor {
@@ -25,38 +325,214 @@ impl LogSculptor {
     /// # Arguments
     ///
     /// * `record_format` - the structural format to use when sculpting records
-    pub fn new(record_format: RecordFormat) -> LogSculptor {
-        LogSculptor { record_format }
+    /// * `options` - settings for the built-in record formats
+    pub fn new(
+        record_format: RecordFormat,
+        options: SculptOptions,
+    ) -> Result<LogSculptor, FormatError> {
+        let mut sculptor = LogSculptor {
+            record_format: RecordFormat::Simple,
+            pattern: None,
+            syslog: None,
+            options,
+        };
+        sculptor.set_record_format(record_format)?;
+        Ok(sculptor)
+    }
+
+    /// Replace the record format, compiling it if it is a pattern or syslog format
+    ///
+    /// The current record format is kept if the new one is invalid
+    ///
+    /// # Arguments
+    ///
+    /// * `record_format` - new record format
+    pub fn set_record_format(&mut self, record_format: RecordFormat) -> Result<(), FormatError> {
+        self.pattern = match &record_format {
+            RecordFormat::Pattern(pattern) => Some(Pattern::new(pattern)?),
+            _ => None,
+        };
+        self.syslog = match &record_format {
+            RecordFormat::Rfc5424(config) => Some(SyslogFormatter::new(Protocol::Rfc5424, config)),
+            RecordFormat::Rfc3164(config) => Some(SyslogFormatter::new(Protocol::Rfc3164, config)),
+            _ => None,
+        };
+        self.record_format = record_format;
+        Ok(())
+    }
+
+    /// Format the optional fields of a `Simple` record as ` (<thread>) <module> <file>:<line>`
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - the record to format fields of
+    fn simple_fields(&self, record: &Record) -> String {
+        let fields = &self.options.fields;
+        let mut out = String::new();
+
+        if fields.thread {
+            out.push_str(&format!(" ({})", thread_name()));
+        }
+
+        if let Some(module) = record.module_path().filter(|_| fields.module) {
+            out.push_str(&format!(" {}", module));
+        }
+
+        let file = record.file().filter(|_| fields.file);
+        let line = record.line().filter(|_| fields.line);
+
+        match (file, line) {
+            (Some(file), Some(line)) => out.push_str(&format!(" {}:{}", file, line)),
+            (Some(file), None) => out.push_str(&format!(" {}", file)),
+            (None, Some(line)) => out.push_str(&format!(" line {}", line)),
+            (None, None) => {}
+        }
+
+        out
     }
 
-    /// Convert a log record into a formatted string, based on the current logger configuration
+    /// Format a record as logfmt pairs
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - the record to format
+    /// * `now` - the time the record was logged
+    fn logfmt(&self, record: &Record, now: &Now) -> String {
+        let fields = &self.options.fields;
+        let mut pairs: Vec<(String, String)> = vec![];
+
+        if let Some(stamp) = &now.stamp {
+            pairs.push(("time".to_string(), stamp.to_string()));
+        }
+
+        pairs.push(("level".to_string(), record.level().as_str().to_lowercase()));
+        pairs.push(("target".to_string(), record.target().to_string()));
+
+        if let Some(file) = record.file().filter(|_| fields.file) {
+            pairs.push(("file".to_string(), file.to_string()));
+        }
+
+        if let Some(line) = record.line().filter(|_| fields.line) {
+            pairs.push(("line".to_string(), line.to_string()));
+        }
+
+        if let Some(module) = record.module_path().filter(|_| fields.module) {
+            pairs.push(("module".to_string(), module.to_string()));
+        }
+
+        if fields.thread {
+            pairs.push(("thread".to_string(), thread_name()));
+        }
+
+        pairs.push(("msg".to_string(), record.args().to_string()));
+
+        for (k, v) in key_values(record) {
+            let v = match v {
+                Value::String(s) => s,
+                v => v.to_string(),
+            };
+            pairs.push((k, v));
+        }
+
+        pairs
+            .iter()
+            .map(|(k, v)| format!("{}={}", logfmt_key(k), logfmt_value(v)))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// Convert a log record into a formatted record, based on the current logger
+    /// configuration
+    ///
+    /// `Simple` and `Pattern` records are split into segments, so that the painter can
+    /// style each part independently, records in other formats are a single `Message`
+    /// segment
     ///
     /// # Arguments
     ///
     /// * `record` - the log record to format
-    pub fn sculpt(&self, record: &Record) -> String {
-        let now = OffsetDateTime::now_utc()
-            .format(&Iso8601::DEFAULT)
-            .expect("Failed to format time as ISO 8601");
+    pub fn sculpt(&self, record: &Record) -> SegmentedRecord {
+        let now = self.options.clock.now();
+
+        if let Some(pattern) = &self.pattern {
+            return pattern.render_segments(record, &now);
+        }
+
+        if let Some(syslog) = &self.syslog {
+            return syslog.format(record, &now).into();
+        }
 
         match &self.record_format {
-            RecordFormat::Json => json!({
-                "time": now,
-                "level": record.level(),
-                "target": record.target(),
-                "message": record.args(),
-            })
-            .to_string(),
+            RecordFormat::Json => {
+                let mut map = Map::new();
+
+                if let Some(stamp) = &now.stamp {
+                    map.insert("time".to_string(), stamp.to_json());
+                }
+
+                map.insert("level".to_string(), json!(record.level()));
+                map.insert("target".to_string(), json!(record.target()));
+                map.insert("message".to_string(), json!(record.args()));
+
+                let fields = &self.options.fields;
+
+                if let Some(file) = record.file().filter(|_| fields.file) {
+                    map.insert("file".to_string(), json!(file));
+                }
+
+                if let Some(line) = record.line().filter(|_| fields.line) {
+                    map.insert("line".to_string(), json!(line));
+                }
+
+                if let Some(module) = record.module_path().filter(|_| fields.module) {
+                    map.insert("module".to_string(), json!(module));
+                }
+
+                if fields.thread {
+                    map.insert("thread".to_string(), json!(thread_name()));
+                }
+
+                let kvs = key_values(record);
+
+                match &self.options.json_kv_key {
+                    Some(key) => {
+                        if !kvs.is_empty() {
+                            map.insert(key.clone(), Value::Object(kvs.into_iter().collect()));
+                        }
+                    }
+                    None => {
+                        // built-in fields take precedence over key-values with the same key
+                        for (k, v) in kvs {
+                            map.entry(k).or_insert(v);
+                        }
+                    }
+                }
+
+                Value::Object(map).to_string().into()
+            }
             RecordFormat::Simple => {
-                format!(
-                    "{} [{}] {} - {}",
-                    now,
-                    record.target(),
-                    record.level(),
-                    record.args()
-                )
+                let mut out = SegmentedRecord::new();
+
+                if let Some(stamp) = &now.stamp {
+                    out.push(Segment::Timestamp, stamp.to_string());
+                    out.push(Segment::Text, " ");
+                }
+
+                out.push(Segment::Text, "[");
+                out.push(Segment::Target, record.target());
+                out.push(Segment::Text, "] ");
+                out.push(Segment::Level, record.level().to_string());
+                out.push(Segment::Fields, self.simple_fields(record));
+                out.push(Segment::Text, " - ");
+                out.push(Segment::Message, record.args().to_string());
+                out.push(Segment::Fields, simple_key_values(&key_values(record)));
+                out
+            }
+            RecordFormat::Logfmt => self.logfmt(record, &now).into(),
+            RecordFormat::Custom(f) => f(record).into(),
+            RecordFormat::Pattern(_) | RecordFormat::Rfc5424(_) | RecordFormat::Rfc3164(_) => {
+                unreachable!("patterns and syslog formats are compiled when they are set")
             }
-            RecordFormat::Custom(f) => f(record),
         }
     }
 }
@@ -68,8 +544,12 @@ mod tests {
 
     #[test]
     fn sculpt_presets_return_non_empty() {
-        for fmt in vec![RecordFormat::Json, RecordFormat::Simple] {
-            let sculptor = LogSculptor::new(fmt);
+        for fmt in [
+            RecordFormat::Json,
+            RecordFormat::Simple,
+            RecordFormat::Logfmt,
+        ] {
+            let sculptor = LogSculptor::new(fmt, SculptOptions::default()).unwrap();
 
             // create normal test record
             let rec = Record::builder()
@@ -78,7 +558,7 @@ mod tests {
                 .target("test")
                 .build();
 
-            assert!(!sculptor.sculpt(&rec).is_empty());
+            assert!(!sculptor.sculpt(&rec).into_text().is_empty());
 
             // create record with empty args and target
             let rec = Record::builder()
@@ -88,7 +568,7 @@ mod tests {
                 .build();
 
             // record should still give non-empty log lines
-            assert!(!sculptor.sculpt(&rec).is_empty());
+            assert!(!sculptor.sculpt(&rec).into_text().is_empty());
         }
     }
 
@@ -115,8 +595,255 @@ mod tests {
             .build();
 
         for (fmt, expected) in test_cases {
-            let sculptor = LogSculptor::new(fmt);
-            assert_eq!(sculptor.sculpt(&rec), expected);
+            let sculptor = LogSculptor::new(fmt, SculptOptions::default()).unwrap();
+            assert_eq!(sculptor.sculpt(&rec).into_text(), expected);
         }
     }
+
+    #[test]
+    fn sculpt_json_includes_typed_key_values() {
+        let kvs: &[(&str, kv::Value)] = &[
+            ("user_id", kv::Value::from(42)),
+            ("ratio", kv::Value::from(0.5)),
+            ("admin", kv::Value::from(false)),
+            ("name", kv::Value::from("bob")),
+            ("level", kv::Value::from("shadowed")),
+        ];
+        let rec = Record::builder()
+            .args(format_args!("login"))
+            .level(Level::Info)
+            .target("test")
+            .key_values(&kvs)
+            .build();
+
+        let sculptor = LogSculptor::new(RecordFormat::Json, SculptOptions::default()).unwrap();
+        let v: Value = serde_json::from_str(&sculptor.sculpt(&rec).into_text()).unwrap();
+
+        assert_eq!(v["message"], "login");
+        assert_eq!(v["level"], "INFO");
+        assert_eq!(v["user_id"], 42);
+        assert_eq!(v["ratio"], 0.5);
+        assert_eq!(v["admin"], false);
+        assert_eq!(v["name"], "bob");
+    }
+
+    #[test]
+    fn sculpt_json_nests_key_values_under_key() {
+        let kvs: &[(&str, kv::Value)] = &[("user_id", kv::Value::from(42))];
+        let rec = Record::builder()
+            .args(format_args!("login"))
+            .level(Level::Info)
+            .key_values(&kvs)
+            .build();
+        let options = SculptOptions {
+            json_kv_key: Some("fields".to_string()),
+            ..Default::default()
+        };
+
+        let sculptor = LogSculptor::new(RecordFormat::Json, options.clone()).unwrap();
+        let v: Value = serde_json::from_str(&sculptor.sculpt(&rec).into_text()).unwrap();
+        assert_eq!(v["fields"]["user_id"], 42);
+        assert!(v.get("user_id").is_none());
+
+        // no nested object is added for records without key-values
+        let rec = Record::builder().args(format_args!("login")).build();
+        let v: Value = serde_json::from_str(&sculptor.sculpt(&rec).into_text()).unwrap();
+        assert!(v.get("fields").is_none());
+    }
+
+    #[test]
+    fn sculpt_simple_appends_key_values() {
+        let kvs: &[(&str, kv::Value)] = &[
+            ("user_id", kv::Value::from(42)),
+            ("admin", kv::Value::from(true)),
+            ("name", kv::Value::from("bob \"the\" builder")),
+        ];
+        let rec = Record::builder()
+            .args(format_args!("login"))
+            .level(Level::Info)
+            .target("test")
+            .key_values(&kvs)
+            .build();
+
+        let sculptor = LogSculptor::new(RecordFormat::Simple, SculptOptions::default()).unwrap();
+        assert!(sculptor
+            .sculpt(&rec)
+            .into_text()
+            .ends_with(r#"[test] INFO - login user_id=42 admin=true name="bob \"the\" builder""#));
+    }
+
+    #[test]
+    fn sculpt_pattern_formats_correctly() {
+        let kvs: &[(&str, kv::Value)] = &[("id", kv::Value::from(7))];
+        let rec = Record::builder()
+            .args(format_args!("foo"))
+            .level(Level::Info)
+            .target("test")
+            .key_values(&kvs)
+            .build();
+
+        let mut sculptor = LogSculptor::new(
+            RecordFormat::Pattern("{level:>5} [{target}] {message} {kv}".to_string()),
+            SculptOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(sculptor.sculpt(&rec).into_text(), " INFO [test] foo id=7");
+
+        // an invalid pattern is rejected and the current format is kept
+        assert_eq!(
+            sculptor.set_record_format(RecordFormat::Pattern("{lvl}".to_string())),
+            Err(FormatError::UnknownPlaceholder("lvl".to_string()))
+        );
+        assert_eq!(sculptor.sculpt(&rec).into_text(), " INFO [test] foo id=7");
+    }
+
+    #[test]
+    fn sculpt_omits_timestamp_without_clock_format() {
+        let rec = Record::builder()
+            .args(format_args!("foo"))
+            .level(Level::Info)
+            .target("test")
+            .build();
+        let options = SculptOptions {
+            clock: Clock::new(None).unwrap(),
+            ..Default::default()
+        };
+
+        let sculptor = LogSculptor::new(RecordFormat::Simple, options.clone()).unwrap();
+        assert_eq!(sculptor.sculpt(&rec).into_text(), "[test] INFO - foo");
+
+        let sculptor = LogSculptor::new(RecordFormat::Json, options).unwrap();
+        let v: Value = serde_json::from_str(&sculptor.sculpt(&rec).into_text()).unwrap();
+        assert!(v.get("time").is_none());
+    }
+
+    #[test]
+    fn sculpt_splits_simple_records_into_segments() {
+        let kvs: &[(&str, kv::Value)] = &[("id", kv::Value::from(7))];
+        let rec = Record::builder()
+            .args(format_args!("foo"))
+            .level(Level::Info)
+            .target("test")
+            .key_values(&kvs)
+            .build();
+        let options = SculptOptions {
+            clock: Clock::new(None).unwrap(),
+            ..Default::default()
+        };
+
+        let sculptor = LogSculptor::new(RecordFormat::Simple, options.clone()).unwrap();
+        assert_eq!(
+            sculptor.sculpt(&rec).segments(),
+            &[
+                (Segment::Text, "[".to_string()),
+                (Segment::Target, "test".to_string()),
+                (Segment::Text, "] ".to_string()),
+                (Segment::Level, "INFO".to_string()),
+                (Segment::Text, " - ".to_string()),
+                (Segment::Message, "foo".to_string()),
+                (Segment::Fields, " id=7".to_string()),
+            ]
+        );
+
+        // records in other formats are a single message segment
+        let sculptor = LogSculptor::new(RecordFormat::Logfmt, options).unwrap();
+        assert_eq!(
+            sculptor.sculpt(&rec).segments(),
+            &[(
+                Segment::Message,
+                "level=info target=test msg=foo id=7".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn sculpt_includes_enabled_record_fields() {
+        let rec = Record::builder()
+            .args(format_args!("foo"))
+            .level(Level::Info)
+            .target("test")
+            .file(Some("src/db.rs"))
+            .line(Some(42))
+            .module_path(Some("app::db"))
+            .build();
+        let options = SculptOptions {
+            clock: Clock::new(None).unwrap(),
+            fields: RecordFields {
+                file: true,
+                line: true,
+                module: true,
+                thread: true,
+            },
+            ..Default::default()
+        };
+        let thread = thread_name();
+
+        let sculptor = LogSculptor::new(RecordFormat::Simple, options.clone()).unwrap();
+        assert_eq!(
+            sculptor.sculpt(&rec).into_text(),
+            format!("[test] INFO ({}) app::db src/db.rs:42 - foo", thread)
+        );
+
+        let sculptor = LogSculptor::new(RecordFormat::Json, options.clone()).unwrap();
+        let v: Value = serde_json::from_str(&sculptor.sculpt(&rec).into_text()).unwrap();
+        assert_eq!(v["file"], "src/db.rs");
+        assert_eq!(v["line"], 42);
+        assert_eq!(v["module"], "app::db");
+        assert_eq!(v["thread"], thread);
+
+        // fields are left out when disabled, or when the record doesn't have them
+        let options = SculptOptions {
+            fields: RecordFields {
+                file: true,
+                ..Default::default()
+            },
+            ..options
+        };
+        let rec = Record::builder()
+            .args(format_args!("foo"))
+            .level(Level::Info)
+            .target("test")
+            .line(Some(42))
+            .build();
+
+        let sculptor = LogSculptor::new(RecordFormat::Simple, options.clone()).unwrap();
+        assert_eq!(sculptor.sculpt(&rec).into_text(), "[test] INFO - foo");
+
+        let sculptor = LogSculptor::new(RecordFormat::Json, options).unwrap();
+        let v: Value = serde_json::from_str(&sculptor.sculpt(&rec).into_text()).unwrap();
+        assert!(v.get("file").is_none());
+        assert!(v.get("line").is_none());
+    }
+
+    #[test]
+    fn sculpt_logfmt_quotes_and_escapes_values() {
+        let kvs: &[(&str, kv::Value)] = &[
+            ("user id", kv::Value::from(42)),
+            ("admin", kv::Value::from(false)),
+            ("query", kv::Value::from("a=b")),
+            ("name", kv::Value::from("bob")),
+            ("empty", kv::Value::from("")),
+        ];
+        let rec = Record::builder()
+            .args(format_args!("said \"hi\"\n\tC:\\"))
+            .level(Level::Warn)
+            .target("app::db")
+            .line(Some(42))
+            .key_values(&kvs)
+            .build();
+        let options = SculptOptions {
+            clock: Clock::new(None).unwrap(),
+            fields: RecordFields {
+                line: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let sculptor = LogSculptor::new(RecordFormat::Logfmt, options).unwrap();
+        assert_eq!(
+            sculptor.sculpt(&rec).into_text(),
+            r#"level=warn target=app::db line=42 msg="said \"hi\"\n\tC:\\" user_id=42 admin=false query="a=b" name=bob empty="""#
+        );
+    }
 }
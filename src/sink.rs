@@ -0,0 +1,242 @@
+//! Output sinks that sculpt, paint, and write log records
+
+use crate::paint::{ColorFormat, LogPainter, Quantization};
+use crate::queue::WriteQueue;
+use crate::sculpt::{FormatError, LogSculptor, RecordFormat, SculptOptions};
+use crate::term::ColorSupport;
+use crate::theme::{Spectral, Theme};
+use crate::write::{Destination, LogWriter};
+use log::{Level, LevelFilter, Record};
+use std::sync::Arc;
+
+/// Configuration for one output sink of a `Logger`
+///
+/// Each sink formats, colors, and writes records independently, so a single logger
+/// can e.g. write colored `Simple` records to the console and uncolored `Json`
+/// records to a file
+pub struct SinkConfig {
+    /// log level filter for this sink (records that pass the logger's filters, but are
+    /// below this severity, will not be written to this sink)
+    pub level: LevelFilter,
+    /// record formatting mode (determines how log records are structurally formatted)
+    pub record_format: RecordFormat,
+    /// color formatting mode (determines how log records are colored)
+    pub color_format: Option<ColorFormat>,
+    /// color theme (determines the color palette used to color log records)
+    pub theme: Box<dyn Theme>,
+    /// switch for enabling log splitting to `stderr` (see `Config::use_stderr`)
+    pub use_stderr: bool,
+    /// output destination for log records (console or file)
+    pub destination: Destination,
+    /// colors supported by console output (see `Config::color_support`)
+    pub color_support: Option<ColorSupport>,
+    /// how colors are quantized for terminals without 24-bit color (see
+    /// `Config::quantization`)
+    pub quantization: Quantization,
+}
+
+impl Default for SinkConfig {
+    /// Return a `SinkConfig` with default values
+    fn default() -> SinkConfig {
+        SinkConfig {
+            level: LevelFilter::Trace,
+            record_format: RecordFormat::Simple,
+            color_format: Some(ColorFormat::Solid),
+            theme: Box::new(Spectral {}),
+            use_stderr: true,
+            destination: Destination::Console,
+            color_support: None,
+            quantization: Quantization::Nearest,
+        }
+    }
+}
+
+/// Sculpt, paint, and write log records to one output destination
+pub struct LogSink {
+    /// log level filter for this sink
+    pub level: LevelFilter,
+    /// painter for logs
+    pub log_painter: LogPainter,
+    /// sculptor for logs
+    pub log_sculptor: LogSculptor,
+    /// writer for logs (shared with records waiting in an async queue)
+    pub log_writer: Arc<LogWriter>,
+}
+
+impl LogSink {
+    /// Create a new LogSink
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - configuration for this sink
+    /// * `options` - settings for the built-in record formats, shared by all sinks
+    pub fn new(config: SinkConfig, options: &SculptOptions) -> Result<LogSink, FormatError> {
+        let log_writer =
+            LogWriter::new(config.destination, config.use_stderr, config.color_support);
+
+        // don't write ANSI color codes to destinations that can't display them
+        let color_format = if log_writer.supports_color() {
+            config.color_format
+        } else {
+            None
+        };
+
+        let mut log_painter = LogPainter::new(config.theme, color_format);
+        log_painter.set_quantization(config.quantization);
+
+        Ok(LogSink {
+            level: config.level,
+            log_sculptor: LogSculptor::new(config.record_format, options.clone())?,
+            log_painter,
+            log_writer: Arc::new(log_writer),
+        })
+    }
+
+    /// Check if records at `level` should be written to this sink
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - level of a log record
+    pub fn enabled(&self, level: Level) -> bool {
+        level <= self.level
+    }
+
+    /// Sculpt, paint, and write a log record
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - the record to log
+    /// * `queue` - queue to write the record from a background thread, or `None` to write
+    ///   it on the calling thread
+    pub fn log(&self, record: &Record, queue: Option<&WriteQueue>) {
+        if !self.enabled(record.level()) {
+            return;
+        }
+
+        let msg = self.log_sculptor.sculpt(record);
+        let msg = self.log_painter.paint_sculpted(
+            msg,
+            record,
+            self.log_writer.color_support(record.level()),
+        );
+        let msg = self.log_writer.encode(record, msg);
+
+        match queue {
+            Some(q) => q.push(Arc::clone(&self.log_writer), msg, record.level()),
+            None => self.log_writer.write(msg, record.level()),
+        }
+    }
+
+    /// Replace the color format, unless this sink's destination can't display colors
+    ///
+    /// # Arguments
+    ///
+    /// * `color_format` - new color format
+    pub fn set_color_format(&mut self, color_format: Option<ColorFormat>) {
+        if self.log_writer.supports_color() {
+            self.log_painter.set_color_format(color_format);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::FileConfig;
+    use crate::testing::TempPath;
+    use std::fs;
+
+    #[test]
+    fn log_respects_sink_level() {
+        let path = TempPath::new("sink-level.log");
+
+        let sink = LogSink::new(
+            SinkConfig {
+                level: LevelFilter::Warn,
+                record_format: RecordFormat::Custom(Box::new(|r| r.args().to_string())),
+                destination: Destination::File(FileConfig::new(&*path)),
+                ..Default::default()
+            },
+            &SculptOptions::default(),
+        )
+        .unwrap();
+
+        for (level, msg) in [
+            (Level::Info, "foo"),
+            (Level::Warn, "bar"),
+            (Level::Error, "baz"),
+        ] {
+            sink.log(
+                &Record::builder()
+                    .args(format_args!("{}", msg))
+                    .level(level)
+                    .build(),
+                None,
+            );
+        }
+
+        // file sinks are never colored, and info records are below the sink's level
+        assert_eq!(fs::read_to_string(&path).unwrap(), "bar\nbaz\n");
+    }
+
+    #[test]
+    fn file_sinks_ignore_color_format() {
+        let path = TempPath::new("sink-color.log");
+        let mut sink = LogSink::new(
+            SinkConfig {
+                destination: Destination::File(FileConfig::new(&*path)),
+                ..Default::default()
+            },
+            &SculptOptions::default(),
+        )
+        .unwrap();
+        let msg = "foo".to_string();
+
+        assert_eq!(
+            sink.log_painter
+                .paint(msg.clone(), Level::Info, ColorSupport::TrueColor),
+            msg
+        );
+
+        sink.set_color_format(Some(ColorFormat::Solid));
+        assert_eq!(
+            sink.log_painter
+                .paint(msg.clone(), Level::Info, ColorSupport::TrueColor),
+            msg
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn journald_sinks_send_journal_entries() {
+        use std::os::unix::net::UnixDatagram;
+
+        let path = TempPath::new("sink-journal.sock");
+        let server = UnixDatagram::bind(&path).unwrap();
+
+        let sink = LogSink::new(
+            SinkConfig {
+                record_format: RecordFormat::Pattern("{message}".to_string()),
+                destination: Destination::Journald(path.to_path_buf()),
+                ..Default::default()
+            },
+            &SculptOptions::default(),
+        )
+        .unwrap();
+        sink.log(
+            &Record::builder()
+                .args(format_args!("foo"))
+                .level(Level::Error)
+                .target("app")
+                .build(),
+            None,
+        );
+
+        let mut buf = [0; 256];
+        let n = server.recv(&mut buf).unwrap();
+        assert_eq!(
+            &buf[..n],
+            b"MESSAGE=foo\nPRIORITY=3\nSYSLOG_IDENTIFIER=app\n"
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! Output to Unix datagram sockets (e.g. a syslog daemon's `/dev/log`)
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+#[cfg(target_os = "linux")]
+use crate::journal;
+
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+
+/// Stand-in for `UnixDatagram` on platforms without Unix sockets, which always fails
+#[cfg(not(unix))]
+struct UnixDatagram;
+
+#[cfg(not(unix))]
+impl UnixDatagram {
+    /// Fail to create a socket
+    fn unbound() -> io::Result<UnixDatagram> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Unix datagram sockets are not supported on this platform",
+        ))
+    }
+
+    /// Fail to send a datagram
+    fn send_to(&self, _buf: &[u8], _path: &std::path::Path) -> io::Result<usize> {
+        Self::unbound().map(|_| 0)
+    }
+}
+
+/// Send each log record as a single datagram to a Unix datagram socket
+pub struct DatagramWriter {
+    /// path of the socket to send records to
+    path: PathBuf,
+    /// unbound socket used to send records, created when the first record is sent
+    socket: Mutex<Option<UnixDatagram>>,
+    /// whether the last record failed to send (so repeated failures are reported once)
+    failing: AtomicBool,
+    /// whether records too large for a datagram are sent as memory files, as journald
+    /// expects (only supported on Linux)
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    journal: bool,
+}
+
+impl DatagramWriter {
+    /// Create a new DatagramWriter
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path of the socket to send records to
+    pub fn new(path: PathBuf) -> DatagramWriter {
+        DatagramWriter {
+            path,
+            socket: Mutex::new(None),
+            failing: AtomicBool::new(false),
+            journal: false,
+        }
+    }
+
+    /// Create a new DatagramWriter for journald's native protocol socket, which sends
+    /// entries too large for a single datagram as sealed memory files (on Linux)
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path of journald's socket
+    pub fn journal(path: PathBuf) -> DatagramWriter {
+        DatagramWriter {
+            journal: true,
+            ..DatagramWriter::new(path)
+        }
+    }
+
+    /// Send a log record, reporting failures to stderr
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - the encoded log record
+    pub fn send(&self, buf: &[u8]) {
+        match self.try_send(buf) {
+            Ok(()) => self.failing.store(false, Ordering::Relaxed),
+            Err(e) => {
+                // only report the first of a run of failures (e.g. while the daemon is down)
+                if !self.failing.swap(true, Ordering::Relaxed) {
+                    let _ = writeln!(
+                        io::stderr(),
+                        "swing: failed to send log record to {}: {}",
+                        self.path.display(),
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    /// Send a log record, creating the socket if needed
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - the encoded log record
+    fn try_send(&self, buf: &[u8]) -> io::Result<()> {
+        let mut socket = self.socket.lock().unwrap();
+
+        if socket.is_none() {
+            *socket = Some(UnixDatagram::unbound()?);
+        }
+
+        if let Some(s) = socket.as_ref() {
+            match s.send_to(buf, &self.path) {
+                #[cfg(target_os = "linux")]
+                Err(e) if self.journal && journal::is_too_large(&e) => {
+                    journal::send_memfd(s, &self.path, buf)?
+                }
+                result => {
+                    result?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::testing::TempPath;
+    use std::fs;
+
+    #[test]
+    fn send_delivers_datagrams() {
+        let path = TempPath::new("socket.sock");
+        let server = UnixDatagram::bind(&path).unwrap();
+        let writer = DatagramWriter::new(path.to_path_buf());
+        let mut buf = [0; 64];
+
+        writer.send(b"foo");
+        writer.send(b"bar baz");
+
+        let n = server.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"foo");
+        let n = server.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"bar baz");
+
+        // records sent while nothing is listening are dropped
+        drop(server);
+        fs::remove_file(&path).unwrap();
+        writer.send(b"lost");
+        assert!(writer.failing.load(Ordering::Relaxed));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn journal_sends_large_entries_as_memfds() {
+        use std::os::unix::fs::FileExt;
+        use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+        use std::{mem, ptr};
+
+        let path = TempPath::new("journal.sock");
+        let server = UnixDatagram::bind(&path).unwrap();
+        let writer = DatagramWriter::journal(path.to_path_buf());
+
+        // larger than the maximum datagram size
+        let entry = vec![b'x'; 16 * 1024 * 1024];
+        writer.send(&entry);
+        assert!(!writer.failing.load(Ordering::Relaxed));
+
+        // the datagram is empty, with the entry's file descriptor attached
+        let mut buf = [0u8; 16];
+        let mut iov = libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+        let mut control = [0u64; 8];
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = mem::size_of_val(&control) as _;
+
+        let n = unsafe { libc::recvmsg(server.as_raw_fd(), &mut msg, 0) };
+        assert_eq!(n, 0);
+
+        let fd = unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            assert_eq!((*cmsg).cmsg_type, libc::SCM_RIGHTS);
+            ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const RawFd)
+        };
+        let seals = unsafe { libc::fcntl(fd, libc::F_GET_SEALS) };
+        assert_ne!(seals & libc::F_SEAL_WRITE, 0);
+
+        // journald reads the file from the start, regardless of its offset
+        let file = unsafe { fs::File::from_raw_fd(fd) };
+        let mut received = vec![0; entry.len()];
+        assert_eq!(file.metadata().unwrap().len(), entry.len() as u64);
+        file.read_exact_at(&mut received, 0).unwrap();
+        assert!(received == entry);
+    }
+}
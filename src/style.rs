@@ -0,0 +1,89 @@
+//! Text styles applied to segments of log records
+
+use crate::Rgb;
+
+/// Source of a color in a `Style`
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum StyleColor {
+    /// the theme's solid color for the record's level
+    Level,
+    /// a fixed color
+    Rgb(Rgb),
+    /// black or white, whichever stands out more against the style's other color (its
+    /// background for a foreground color, and vice versa), or against the theme's solid
+    /// color for the record's level if the other color isn't set
+    Contrast,
+}
+
+/// Text style of a log line, or of one segment of a log record
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Style {
+    /// foreground color (`None` keeps the terminal's default color)
+    pub fg: Option<StyleColor>,
+    /// background color (`None` keeps the terminal's default color)
+    pub bg: Option<StyleColor>,
+    /// bold (increased intensity) text
+    pub bold: bool,
+    /// dim (decreased intensity) text
+    pub dim: bool,
+    /// italic text
+    pub italic: bool,
+    /// underlined text
+    pub underline: bool,
+    /// blinking text
+    pub blink: bool,
+    /// reversed foreground and background colors
+    pub reverse: bool,
+    /// pad the text with a space on each side (inside the background color), e.g. for
+    /// badges
+    pub pad: bool,
+}
+
+impl Style {
+    /// Get the style of a level badge: bold, padded text in black or white on the theme's
+    /// solid color for the record's level (e.g. white on red ` ERROR `)
+    pub fn badge() -> Style {
+        Style {
+            fg: Some(StyleColor::Contrast),
+            bg: Some(StyleColor::Level),
+            bold: true,
+            pad: true,
+            ..Style::default()
+        }
+    }
+
+    /// Get the SGR parameters for the text attributes of this style (not its colors)
+    pub(crate) fn attribute_codes(&self) -> Vec<String> {
+        [
+            (self.bold, "1"),
+            (self.dim, "2"),
+            (self.italic, "3"),
+            (self.underline, "4"),
+            (self.blink, "5"),
+            (self.reverse, "7"),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, code)| code.to_string())
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attribute_codes_follow_sgr_order() {
+        assert!(Style::default().attribute_codes().is_empty());
+
+        let style = Style {
+            reverse: true,
+            bold: true,
+            underline: true,
+            fg: Some(StyleColor::Level),
+            ..Style::default()
+        };
+        assert_eq!(style.attribute_codes(), ["1", "4", "7"]);
+    }
+}
@@ -0,0 +1,343 @@
+//! Syslog message formats (RFC 5424 and RFC 3164)
+
+use crate::sculpt::{key_values, simple_key_values};
+use crate::timestamp::Now;
+use log::{Level, Record};
+use serde::Deserialize;
+use std::{env, fs, process};
+
+/// Path of the local syslog daemon's socket on most Unix systems
+pub const SYSLOG_SOCKET: &str = "/dev/log";
+
+/// Syslog facility, indicating the type of program that logged a message
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Facility {
+    /// kernel messages
+    Kern = 0,
+    /// user-level messages
+    User = 1,
+    /// mail system
+    Mail = 2,
+    /// system daemons
+    Daemon = 3,
+    /// security/authorization messages
+    Auth = 4,
+    /// messages generated internally by the syslog daemon
+    Syslog = 5,
+    /// line printer subsystem
+    Lpr = 6,
+    /// network news subsystem
+    News = 7,
+    /// UUCP subsystem
+    Uucp = 8,
+    /// clock daemon
+    Cron = 9,
+    /// security/authorization messages (private)
+    AuthPriv = 10,
+    /// FTP daemon
+    Ftp = 11,
+    /// local use 0
+    Local0 = 16,
+    /// local use 1
+    Local1 = 17,
+    /// local use 2
+    Local2 = 18,
+    /// local use 3
+    Local3 = 19,
+    /// local use 4
+    Local4 = 20,
+    /// local use 5
+    Local5 = 21,
+    /// local use 6
+    Local6 = 22,
+    /// local use 7
+    Local7 = 23,
+}
+
+/// Header fields of syslog messages
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SyslogConfig {
+    /// facility of messages
+    pub facility: Facility,
+    /// name of the host (`None` uses the system's hostname)
+    pub hostname: Option<String>,
+    /// name of the application (`None` uses the executable's file name)
+    pub app_name: Option<String>,
+    /// process id (`None` uses the id of the current process)
+    pub procid: Option<u32>,
+}
+
+impl Default for SyslogConfig {
+    /// Return a `SyslogConfig` with default values
+    fn default() -> SyslogConfig {
+        SyslogConfig {
+            facility: Facility::User,
+            hostname: None,
+            app_name: None,
+            procid: None,
+        }
+    }
+}
+
+/// Syslog protocol version
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Protocol {
+    /// RFC 5424 (`<PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID MSGID SD MSG`)
+    Rfc5424,
+    /// RFC 3164, a.k.a. BSD syslog (`<PRI>Mmm dd hh:mm:ss HOSTNAME TAG[PID]: MSG`)
+    Rfc3164,
+}
+
+/// Syslog message formatter with its header fields resolved
+#[derive(Clone, Debug)]
+pub struct SyslogFormatter {
+    /// protocol version of messages
+    protocol: Protocol,
+    /// facility of messages
+    facility: Facility,
+    /// name of the host, if known
+    hostname: Option<String>,
+    /// name of the application, if known
+    app_name: Option<String>,
+    /// process id
+    procid: u32,
+}
+
+/// Map a log level to a syslog severity
+///
+/// # Arguments
+///
+/// * `level` - the log level to map
+pub fn severity(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+/// Get the system's hostname
+fn system_hostname() -> Option<String> {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| fs::read_to_string("/etc/hostname"))
+        .ok()
+        .or_else(|| env::var("HOSTNAME").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+}
+
+/// Get the file name of the current executable
+fn executable_name() -> Option<String> {
+    env::current_exe()
+        .ok()?
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+}
+
+/// Make a header field valid for RFC 5424 (printable ASCII, at most `max_len` characters),
+/// or `-` if it is empty
+///
+/// # Arguments
+///
+/// * `field` - the header field
+/// * `max_len` - maximum length of the field
+fn header_field(field: Option<&str>, max_len: usize) -> String {
+    let field: String = field
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_graphic() { c } else { '_' })
+        .take(max_len)
+        .collect();
+
+    if field.is_empty() {
+        "-".to_string()
+    } else {
+        field
+    }
+}
+
+impl SyslogFormatter {
+    /// Create a new SyslogFormatter
+    ///
+    /// # Arguments
+    ///
+    /// * `protocol` - protocol version of messages
+    /// * `config` - header fields of messages
+    pub fn new(protocol: Protocol, config: &SyslogConfig) -> SyslogFormatter {
+        SyslogFormatter {
+            protocol,
+            facility: config.facility,
+            hostname: config.hostname.clone().or_else(system_hostname),
+            app_name: config.app_name.clone().or_else(executable_name),
+            procid: config.procid.unwrap_or_else(process::id),
+        }
+    }
+
+    /// Format a log record as a syslog message
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - the log record to format
+    /// * `now` - the time the record was logged
+    pub fn format(&self, record: &Record, now: &Now) -> String {
+        let pri = self.facility as u8 * 8 + severity(record.level());
+        let msg = format!(
+            "{}{}",
+            record.args(),
+            simple_key_values(&key_values(record))
+        );
+
+        match self.protocol {
+            Protocol::Rfc5424 => {
+                // RFC 5424 timestamps have at most microsecond precision
+                let t = now.time;
+                let timestamp = match now.stamp {
+                    Some(_) => {
+                        let offset = t.offset();
+                        let offset = if offset.is_utc() {
+                            "Z".to_string()
+                        } else {
+                            format!(
+                                "{}{:02}:{:02}",
+                                if offset.is_negative() { '-' } else { '+' },
+                                offset.whole_hours().abs(),
+                                offset.minutes_past_hour().abs()
+                            )
+                        };
+                        format!(
+                            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}{}",
+                            t.year(),
+                            t.month() as u8,
+                            t.day(),
+                            t.hour(),
+                            t.minute(),
+                            t.second(),
+                            t.microsecond(),
+                            offset
+                        )
+                    }
+                    None => "-".to_string(),
+                };
+
+                format!(
+                    "<{}>1 {} {} {} {} - - {}",
+                    pri,
+                    timestamp,
+                    header_field(self.hostname.as_deref(), 255),
+                    header_field(self.app_name.as_deref(), 48),
+                    self.procid,
+                    msg
+                )
+            }
+            Protocol::Rfc3164 => {
+                let t = now.time;
+                let tag: String = self
+                    .app_name
+                    .as_deref()
+                    .unwrap_or("swing")
+                    .chars()
+                    .filter(|c| c.is_ascii_graphic() && !matches!(c, ':' | '[' | ']'))
+                    .take(32)
+                    .collect();
+
+                format!(
+                    "<{}>{} {:>2} {:02}:{:02}:{:02} {} {}[{}]: {}",
+                    pri,
+                    &t.month().to_string()[..3],
+                    t.day(),
+                    t.hour(),
+                    t.minute(),
+                    t.second(),
+                    match &self.hostname {
+                        Some(_) => header_field(self.hostname.as_deref(), 255),
+                        None => "localhost".to_string(),
+                    },
+                    tag,
+                    self.procid,
+                    msg
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestamp::Stamp;
+    use log::kv;
+    use time::{OffsetDateTime, UtcOffset};
+
+    // helpers
+
+    /// Format a record at 2022-07-05T20:25:31.108560826+02:00 as a syslog message
+    ///
+    /// # Arguments
+    ///
+    /// * `protocol` - protocol version of the message
+    /// * `level` - level of the record
+    /// * `stamp` - whether timestamps are enabled
+    fn format(protocol: Protocol, level: Level, stamp: bool) -> String {
+        let kvs: &[(&str, kv::Value)] = &[("id", kv::Value::from(7))];
+        let rec = Record::builder()
+            .args(format_args!("foo"))
+            .level(level)
+            .key_values(&kvs)
+            .build();
+        let now = Now {
+            time: OffsetDateTime::from_unix_timestamp_nanos(1_657_045_531_108_560_826)
+                .unwrap()
+                .to_offset(UtcOffset::from_hms(2, 0, 0).unwrap()),
+            stamp: stamp.then(|| Stamp::Text(String::new())),
+        };
+        let config = SyslogConfig {
+            facility: Facility::Local3,
+            hostname: Some("web 1".to_string()),
+            app_name: Some("api".to_string()),
+            procid: Some(4242),
+        };
+
+        SyslogFormatter::new(protocol, &config).format(&rec, &now)
+    }
+
+    // tests
+
+    #[test]
+    fn format_rfc5424_messages() {
+        assert_eq!(
+            format(Protocol::Rfc5424, Level::Warn, true),
+            "<156>1 2022-07-05T20:25:31.108560+02:00 web_1 api 4242 - - foo id=7"
+        );
+        assert_eq!(
+            format(Protocol::Rfc5424, Level::Trace, false),
+            "<159>1 - web_1 api 4242 - - foo id=7"
+        );
+    }
+
+    #[test]
+    fn format_rfc3164_messages() {
+        assert_eq!(
+            format(Protocol::Rfc3164, Level::Error, true),
+            "<155>Jul  5 20:25:31 web_1 api[4242]: foo id=7"
+        );
+    }
+
+    #[test]
+    fn severity_maps_levels() {
+        let severities: Vec<u8> = [
+            Level::Error,
+            Level::Warn,
+            Level::Info,
+            Level::Debug,
+            Level::Trace,
+        ]
+        .iter()
+        .map(|l| severity(*l))
+        .collect();
+
+        assert_eq!(severities, vec![3, 4, 6, 7, 7]);
+    }
+}
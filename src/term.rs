@@ -0,0 +1,141 @@
+//! Terminal color capability detection
+
+use serde::Deserialize;
+use std::env;
+
+/// Colors that an output stream can display
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorSupport {
+    /// no colors (records are written without ANSI escape codes)
+    Off,
+    /// the 16 standard ANSI colors
+    Ansi16,
+    /// the 256 color xterm palette
+    Ansi256,
+    /// 24-bit RGB colors
+    TrueColor,
+}
+
+/// Detect the colors supported by an output stream from the environment
+///
+/// Follows the `NO_COLOR` (<https://no-color.org>) and `CLICOLOR`/`CLICOLOR_FORCE`
+/// (<https://bixense.com/clicolors>) conventions, then uses `COLORTERM` and `TERM` to
+/// determine how many colors the terminal can display
+///
+/// # Arguments
+///
+/// * `var` - function returning the value of an environment variable, if it is set
+/// * `is_tty` - whether the stream is a terminal
+fn detect_with(var: impl Fn(&str) -> Option<String>, is_tty: bool) -> ColorSupport {
+    let set = |name: &str| var(name).filter(|v| !v.is_empty());
+
+    if set("NO_COLOR").is_some() {
+        return ColorSupport::Off;
+    }
+
+    let forced = set("CLICOLOR_FORCE").is_some_and(|v| v != "0");
+
+    if !forced && (!is_tty || set("CLICOLOR").as_deref() == Some("0")) {
+        return ColorSupport::Off;
+    }
+
+    let colorterm = set("COLORTERM").unwrap_or_default().to_lowercase();
+    let term = set("TERM").unwrap_or_default().to_lowercase();
+
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        ColorSupport::TrueColor
+    } else if term == "dumb" && !forced {
+        ColorSupport::Off
+    } else if term.contains("truecolor") || term.contains("24bit") || term.contains("direct") {
+        ColorSupport::TrueColor
+    } else if term.contains("256color") {
+        ColorSupport::Ansi256
+    } else {
+        ColorSupport::Ansi16
+    }
+}
+
+/// Detect the colors supported by an output stream
+///
+/// # Arguments
+///
+/// * `is_tty` - whether the stream is a terminal
+pub fn detect(is_tty: bool) -> ColorSupport {
+    detect_with(|name| env::var(name).ok(), is_tty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // helpers
+
+    /// Detect color support with the environment variables in `vars`
+    ///
+    /// # Arguments
+    ///
+    /// * `vars` - environment variables as `(name, value)` pairs
+    /// * `is_tty` - whether the stream is a terminal
+    fn detect_env(vars: &[(&str, &str)], is_tty: bool) -> ColorSupport {
+        let vars: HashMap<&str, &str> = vars.iter().cloned().collect();
+        detect_with(|name| vars.get(name).map(|v| v.to_string()), is_tty)
+    }
+
+    // tests
+
+    #[test]
+    fn detect_disables_colors_for_pipes() {
+        assert_eq!(
+            detect_env(&[("COLORTERM", "truecolor")], false),
+            ColorSupport::Off
+        );
+        assert_eq!(
+            detect_env(&[("CLICOLOR_FORCE", "0"), ("TERM", "xterm")], false),
+            ColorSupport::Off
+        );
+    }
+
+    #[test]
+    fn detect_respects_color_conventions() {
+        // NO_COLOR wins over everything else
+        assert_eq!(
+            detect_env(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")], true),
+            ColorSupport::Off
+        );
+        // an empty NO_COLOR is ignored
+        assert_eq!(
+            detect_env(&[("NO_COLOR", ""), ("TERM", "xterm")], true),
+            ColorSupport::Ansi16
+        );
+        assert_eq!(detect_env(&[("CLICOLOR", "0")], true), ColorSupport::Off);
+        assert_eq!(
+            detect_env(&[("CLICOLOR", "0"), ("CLICOLOR_FORCE", "1")], false),
+            ColorSupport::Ansi16
+        );
+        assert_eq!(detect_env(&[("TERM", "dumb")], true), ColorSupport::Off);
+    }
+
+    #[test]
+    fn detect_reads_terminal_color_depth() {
+        assert_eq!(
+            detect_env(&[("COLORTERM", "truecolor"), ("TERM", "xterm")], true),
+            ColorSupport::TrueColor
+        );
+        assert_eq!(
+            detect_env(&[("COLORTERM", "24bit")], true),
+            ColorSupport::TrueColor
+        );
+        assert_eq!(
+            detect_env(&[("TERM", "xterm-direct")], true),
+            ColorSupport::TrueColor
+        );
+        assert_eq!(
+            detect_env(&[("TERM", "screen-256color")], true),
+            ColorSupport::Ansi256
+        );
+        assert_eq!(detect_env(&[("TERM", "vt100")], true), ColorSupport::Ansi16);
+        assert_eq!(detect_env(&[], true), ColorSupport::Ansi16);
+    }
+}
@@ -0,0 +1,71 @@
+//! Helpers shared by tests in several modules
+
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// Path in the temporary directory that is deleted, with anything created at it, when
+/// dropped
+pub struct TempPath {
+    /// the path
+    path: PathBuf,
+}
+
+impl TempPath {
+    /// Reserve a path named after the process and `name` in the temporary directory,
+    /// removing anything left at it by an earlier run
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name that is unique among the crate's tests, with any file extension
+    pub fn new(name: &str) -> TempPath {
+        let path = TempPath {
+            path: std::env::temp_dir().join(format!("swing-{}-{}", std::process::id(), name)),
+        };
+        path.remove();
+        path
+    }
+
+    /// Create an empty directory at a new temporary path
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name that is unique among the crate's tests
+    pub fn dir(name: &str) -> TempPath {
+        let path = TempPath::new(name);
+        fs::create_dir_all(&path.path).unwrap();
+        path
+    }
+
+    /// Remove the file or directory at the path, if there is one
+    fn remove(&self) {
+        if self.path.is_dir() {
+            let _ = fs::remove_dir_all(&self.path);
+        } else {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+impl Deref for TempPath {
+    type Target = Path;
+
+    /// Get the path
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TempPath {
+    /// Get the path
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempPath {
+    /// Remove the file or directory at the path
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
@@ -3,7 +3,9 @@
 //! See examples/custom-theme.rs for an example of defining a
 //! custom theme
 
-use crate::color::{Color, Rgb, RgbRange};
+use crate::color::{Color, ColorRamp, Rgb, RgbRange};
+use crate::sculpt::Segment;
+use crate::style::{Style, StyleColor};
 use log::Level;
 
 /// Define a log level specific color palette to be injected into
@@ -13,6 +15,46 @@ pub trait Theme: Send + Sync {
     fn solid(&self, level: Level) -> Rgb;
     /// return the bounding color range for this theme at each level
     fn range(&self, level: Level) -> RgbRange;
+    /// return the color stops that gradients move through at each level (defaults to the
+    /// start and end of `range`, override to use three or more colors)
+    fn ramp(&self, level: Level) -> ColorRamp {
+        self.range(level).into()
+    }
+    /// return the colors that the `Hashed` color format picks from at each level (defaults
+    /// to none, which picks colors along the level's `ramp` instead)
+    fn palette(&self, _level: Level) -> Vec<Rgb> {
+        vec![]
+    }
+    /// return the text style of whole log lines at each level (defaults to bold warnings and
+    /// errors), where the foreground color is ignored, since color formats choose their own
+    fn level_style(&self, level: Level) -> Style {
+        Style {
+            bold: level <= Level::Warn,
+            ..Style::default()
+        }
+    }
+    /// return the style of each record segment at each level, for the `Segmented` color
+    /// format (defaults to a dim timestamp and fields, a bold level and a target in the
+    /// `solid` color, a message in the `level_style`, and plain text elsewhere)
+    fn segment_style(&self, level: Level, segment: Segment) -> Style {
+        match segment {
+            Segment::Timestamp | Segment::Fields => Style {
+                dim: true,
+                ..Style::default()
+            },
+            Segment::Level => Style {
+                fg: Some(StyleColor::Level),
+                bold: true,
+                ..Style::default()
+            },
+            Segment::Target => Style {
+                fg: Some(StyleColor::Level),
+                ..Style::default()
+            },
+            Segment::Message => self.level_style(level),
+            Segment::Text => Style::default(),
+        }
+    }
 }
 
 /// Basic log level colors
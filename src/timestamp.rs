@@ -0,0 +1,327 @@
+//! Timestamps added to log records
+
+use crate::sculpt::FormatError;
+use serde::Deserialize;
+use serde_json::{Number, Value};
+use std::fmt;
+use std::time::{Duration, Instant};
+use time::format_description::well_known::{Iso8601, Rfc2822, Rfc3339};
+use time::format_description::{self, OwnedFormatItem};
+use time::{OffsetDateTime, UtcOffset};
+
+/// Time zone offset of timestamps
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeOffset {
+    /// UTC time
+    Utc,
+    /// local time, using the system's UTC offset when the logger is created
+    ///
+    /// If the local offset can't be determined (e.g. on some platforms when multiple
+    /// threads are running), UTC time is used instead
+    Local,
+}
+
+/// Format of timestamps
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampFormat {
+    /// ISO 8601, e.g. `2022-07-31T20:25:31.108560826Z`
+    Iso8601,
+    /// RFC 3339, e.g. `2022-07-31T20:25:31.108560826Z`
+    Rfc3339,
+    /// RFC 2822, e.g. `Sun, 31 Jul 2022 20:25:31 +0000`
+    Rfc2822,
+    /// seconds since the Unix epoch
+    UnixSeconds,
+    /// milliseconds since the Unix epoch
+    UnixMillis,
+    /// nanoseconds since the Unix epoch
+    UnixNanos,
+    /// custom format description using the `time` crate's (version 2) syntax, e.g.
+    /// `[year]-[month]-[day] [hour]:[minute]:[second].[subsecond digits:3]`
+    Custom(String),
+    /// seconds elapsed since the logger was created, with millisecond precision
+    /// (e.g. `12.345`)
+    Uptime,
+}
+
+/// Timestamp configuration
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TimestampConfig {
+    /// time zone offset of timestamps (unused for Unix epoch and uptime formats)
+    pub offset: TimeOffset,
+    /// format of timestamps
+    pub format: TimestampFormat,
+}
+
+impl Default for TimestampConfig {
+    /// Return a `TimestampConfig` with default values
+    fn default() -> TimestampConfig {
+        TimestampConfig {
+            offset: TimeOffset::Utc,
+            format: TimestampFormat::Iso8601,
+        }
+    }
+}
+
+/// Timestamp format with custom format descriptions parsed
+#[derive(Clone, Debug)]
+enum CompiledFormat {
+    /// ISO 8601
+    Iso8601,
+    /// RFC 3339
+    Rfc3339,
+    /// RFC 2822
+    Rfc2822,
+    /// seconds since the Unix epoch
+    UnixSeconds,
+    /// milliseconds since the Unix epoch
+    UnixMillis,
+    /// nanoseconds since the Unix epoch
+    UnixNanos,
+    /// parsed custom format description
+    Custom(OwnedFormatItem),
+    /// seconds since the logger was created
+    Uptime,
+}
+
+/// Formatted timestamp of a record
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Stamp {
+    /// timestamp formatted as text
+    Text(String),
+    /// integer timestamp (Unix epoch formats)
+    Integer(i64),
+    /// time elapsed since the logger was created
+    Uptime(Duration),
+}
+
+impl Stamp {
+    /// Convert this timestamp into a JSON value (a number for numeric timestamps)
+    pub fn to_json(&self) -> Value {
+        match self {
+            Stamp::Text(s) => Value::from(s.as_str()),
+            Stamp::Integer(n) => Value::from(*n),
+            Stamp::Uptime(d) => {
+                Number::from_f64(d.as_millis() as f64 / 1000.0).map_or(Value::Null, Value::Number)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Stamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stamp::Text(s) => write!(f, "{}", s),
+            Stamp::Integer(n) => write!(f, "{}", n),
+            Stamp::Uptime(d) => write!(f, "{}.{:03}", d.as_secs(), d.subsec_millis()),
+        }
+    }
+}
+
+/// Time a record was logged
+pub struct Now {
+    /// wall clock time, in the configured offset
+    pub time: OffsetDateTime,
+    /// formatted timestamp (`None` if timestamps are omitted)
+    pub stamp: Option<Stamp>,
+}
+
+/// Source of timestamps for log records
+#[derive(Clone, Debug)]
+pub struct Clock {
+    /// offset applied to the current time
+    offset: UtcOffset,
+    /// timestamp format (`None` omits timestamps)
+    format: Option<CompiledFormat>,
+    /// time the clock was created, for uptime timestamps
+    start: Instant,
+}
+
+impl Default for Clock {
+    /// Return a `Clock` producing ISO 8601 timestamps in UTC
+    fn default() -> Clock {
+        Clock {
+            offset: UtcOffset::UTC,
+            format: Some(CompiledFormat::Iso8601),
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock {
+    /// Create a new Clock
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - timestamp configuration (`None` omits timestamps)
+    pub fn new(config: Option<&TimestampConfig>) -> Result<Clock, FormatError> {
+        let config = match config {
+            Some(config) => config,
+            None => {
+                return Ok(Clock {
+                    format: None,
+                    ..Default::default()
+                })
+            }
+        };
+
+        let offset = match config.offset {
+            TimeOffset::Utc => UtcOffset::UTC,
+            TimeOffset::Local => UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC),
+        };
+
+        let format = match &config.format {
+            TimestampFormat::Iso8601 => CompiledFormat::Iso8601,
+            TimestampFormat::Rfc3339 => CompiledFormat::Rfc3339,
+            TimestampFormat::Rfc2822 => CompiledFormat::Rfc2822,
+            TimestampFormat::UnixSeconds => CompiledFormat::UnixSeconds,
+            TimestampFormat::UnixMillis => CompiledFormat::UnixMillis,
+            TimestampFormat::UnixNanos => CompiledFormat::UnixNanos,
+            TimestampFormat::Custom(format) => {
+                let items = format_description::parse_owned::<2>(format).map_err(|e| {
+                    FormatError::InvalidTimeFormat {
+                        format: format.clone(),
+                        reason: e.to_string(),
+                    }
+                })?;
+                CompiledFormat::Custom(items)
+            }
+            TimestampFormat::Uptime => CompiledFormat::Uptime,
+        };
+
+        Ok(Clock {
+            offset,
+            format: Some(format),
+            start: Instant::now(),
+        })
+    }
+
+    /// Get the current time and its formatted timestamp
+    pub fn now(&self) -> Now {
+        self.at(OffsetDateTime::now_utc())
+    }
+
+    /// Get the formatted timestamp for a time
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - the time to format
+    fn at(&self, time: OffsetDateTime) -> Now {
+        let time = time.to_offset(self.offset);
+        let text = |s: Result<String, time::error::Format>| {
+            Stamp::Text(s.expect("Failed to format timestamp"))
+        };
+
+        let stamp = self.format.as_ref().map(|format| match format {
+            CompiledFormat::Iso8601 => text(time.format(&Iso8601::DEFAULT)),
+            CompiledFormat::Rfc3339 => text(time.format(&Rfc3339)),
+            CompiledFormat::Rfc2822 => text(time.format(&Rfc2822)),
+            CompiledFormat::UnixSeconds => Stamp::Integer(time.unix_timestamp()),
+            CompiledFormat::UnixMillis => {
+                Stamp::Integer((time.unix_timestamp_nanos() / 1_000_000) as i64)
+            }
+            CompiledFormat::UnixNanos => Stamp::Integer(time.unix_timestamp_nanos() as i64),
+            CompiledFormat::Custom(items) => text(time.format(items)),
+            CompiledFormat::Uptime => Stamp::Uptime(self.start.elapsed()),
+        });
+
+        Now { time, stamp }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // helpers
+
+    /// Format 2022-07-31T20:25:31.108560826Z using `format`
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - the timestamp format to use
+    fn stamp(format: TimestampFormat) -> Stamp {
+        let clock = Clock::new(Some(&TimestampConfig {
+            offset: TimeOffset::Utc,
+            format,
+        }))
+        .unwrap();
+        let time = OffsetDateTime::from_unix_timestamp_nanos(1_659_299_131_108_560_826).unwrap();
+
+        clock.at(time).stamp.unwrap()
+    }
+
+    // tests
+
+    #[test]
+    fn clock_formats_timestamps() {
+        let text = |s: &str| Stamp::Text(s.to_string());
+
+        assert_eq!(
+            stamp(TimestampFormat::Iso8601),
+            text("2022-07-31T20:25:31.108560826Z")
+        );
+        assert_eq!(
+            stamp(TimestampFormat::Rfc3339),
+            text("2022-07-31T20:25:31.108560826Z")
+        );
+        assert_eq!(
+            stamp(TimestampFormat::Rfc2822),
+            text("Sun, 31 Jul 2022 20:25:31 +0000")
+        );
+        assert_eq!(
+            stamp(TimestampFormat::UnixSeconds),
+            Stamp::Integer(1_659_299_131)
+        );
+        assert_eq!(
+            stamp(TimestampFormat::UnixMillis),
+            Stamp::Integer(1_659_299_131_108)
+        );
+        assert_eq!(
+            stamp(TimestampFormat::UnixNanos),
+            Stamp::Integer(1_659_299_131_108_560_826)
+        );
+        assert_eq!(
+            stamp(TimestampFormat::Custom(
+                "[hour]:[minute]:[second].[subsecond digits:3]".to_string()
+            )),
+            text("20:25:31.108")
+        );
+    }
+
+    #[test]
+    fn clock_measures_uptime() {
+        let clock = Clock::new(Some(&TimestampConfig {
+            offset: TimeOffset::Local,
+            format: TimestampFormat::Uptime,
+        }))
+        .unwrap();
+
+        match clock.now().stamp {
+            Some(Stamp::Uptime(d)) => assert!(d < Duration::from_secs(60)),
+            stamp => panic!("expected an uptime timestamp, got {:?}", stamp),
+        }
+
+        let stamp = Stamp::Uptime(Duration::from_millis(12_345));
+        assert_eq!(stamp.to_string(), "12.345");
+        assert_eq!(stamp.to_json(), serde_json::json!(12.345));
+    }
+
+    #[test]
+    fn clock_omits_timestamps_without_config() {
+        assert!(Clock::new(None).unwrap().now().stamp.is_none());
+    }
+
+    #[test]
+    fn clock_rejects_invalid_custom_format() {
+        let err = Clock::new(Some(&TimestampConfig {
+            offset: TimeOffset::Utc,
+            format: TimestampFormat::Custom("[hour".to_string()),
+        }))
+        .unwrap_err();
+
+        assert!(matches!(err, FormatError::InvalidTimeFormat { .. }));
+    }
+}
@@ -0,0 +1,204 @@
+//! Reloading a logger's configuration when its configuration file changes
+
+use crate::{Config, LoggerHandle};
+use log::{Level, Record};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+/// Target of the messages logged when a watched configuration file is reloaded
+pub const WATCHER_TARGET: &str = "swing";
+
+/// Name of the background thread that watches a configuration file
+const WATCHER_THREAD_NAME: &str = "swing-config-watcher";
+
+/// Background thread that reloads a logger's configuration when its configuration file
+/// changes (see `LoggerHandle::watch_config_file`)
+///
+/// The file is no longer watched once the `ConfigWatcher` is dropped
+#[must_use = "the configuration file is no longer watched when the watcher is dropped"]
+pub struct ConfigWatcher {
+    /// channel used to stop the watcher thread (closed when dropped)
+    stop: Option<Sender<()>>,
+    /// handle to the watcher thread
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ConfigWatcher {
+    /// Create a new ConfigWatcher and start its watcher thread
+    ///
+    /// # Arguments
+    ///
+    /// * `handle` - handle to the logger to reconfigure
+    /// * `path` - path of the configuration file
+    /// * `interval` - time between checks for changes
+    pub(crate) fn new(handle: LoggerHandle, path: PathBuf, interval: Duration) -> ConfigWatcher {
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        // take the file's version before starting the thread, so that changes made
+        // after the watcher is created are never missed
+        let mut version = file_version(&path);
+
+        let thread = thread::Builder::new()
+            .name(WATCHER_THREAD_NAME.to_string())
+            .spawn(move || {
+                // every message on the channel, or the channel being closed, stops the thread
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    let current = file_version(&path);
+
+                    // skip unchanged files, and files that are missing while being replaced
+                    if current.is_none() || current == version {
+                        continue;
+                    }
+
+                    version = current;
+                    reload(&handle, &path);
+                }
+            })
+            .expect("Failed to spawn config watcher thread");
+
+        ConfigWatcher {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for ConfigWatcher {
+    /// Stop the watcher thread
+    fn drop(&mut self) {
+        drop(self.stop.take());
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Get the modification time and size of a file, used to notice that it has changed
+///
+/// # Arguments
+///
+/// * `path` - path of the file
+fn file_version(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Load a configuration file and apply it to a logger, logging whether it succeeded
+///
+/// # Arguments
+///
+/// * `handle` - handle to the logger to reconfigure
+/// * `path` - path of the configuration file
+fn reload(handle: &LoggerHandle, path: &Path) {
+    let result = Config::from_file(path)
+        .map_err(|e| e.to_string())
+        .and_then(|config| handle.set_config(config).map_err(|e| e.to_string()));
+
+    match result {
+        Ok(()) => handle.log(
+            &Record::builder()
+                .args(format_args!("reloaded config from {}", path.display()))
+                .level(Level::Info)
+                .target(WATCHER_TARGET)
+                .build(),
+        ),
+        Err(e) => handle.log(
+            &Record::builder()
+                .args(format_args!(
+                    "failed to reload config from {}, keeping the current config: {}",
+                    path.display(),
+                    e
+                ))
+                .level(Level::Error)
+                .target(WATCHER_TARGET)
+                .build(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempPath;
+    use crate::Logger;
+    use log::{Log, Metadata};
+    use std::time::Instant;
+
+    // helpers
+
+    /// Wait up to a few seconds for a condition to become true
+    fn wait_for<F: Fn() -> bool>(condition: F) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+
+        while Instant::now() < deadline {
+            if condition() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        condition()
+    }
+
+    /// Flush a logger, then read its log file
+    fn logged(logger: &Logger, path: &Path) -> String {
+        logger.flush();
+        fs::read_to_string(path).unwrap_or_default()
+    }
+
+    // tests
+
+    #[test]
+    fn watcher_reloads_changed_config() {
+        let config_path = TempPath::new("watch.toml");
+        let log_path = TempPath::new("watch.log");
+
+        let config = |level: &str| {
+            format!(
+                "level = \"{}\"\nrecord_format = {{ pattern = \"{{level}} {{target}} - {{message}}\" }}\n\
+                 destination = {{ file = {{ path = {:?} }} }}\n",
+                level,
+                log_path.display().to_string()
+            )
+        };
+        fs::write(&config_path, config("info")).unwrap();
+
+        let logger = Logger::with_config(Config::from_file(&config_path).unwrap());
+        let watcher = logger
+            .handle()
+            .watch_config_file(&config_path, Duration::from_millis(10));
+        let debug = Metadata::builder().level(Level::Debug).build();
+        assert!(!logger.enabled(&debug));
+
+        // valid changes are applied
+        fs::write(&config_path, config("debug")).unwrap();
+        assert!(wait_for(|| logger.enabled(&debug)));
+        assert!(wait_for(
+            || logged(&logger, &log_path).contains("INFO swing - reloaded config from")
+        ));
+
+        // invalid changes are reported, and the current config is kept
+        fs::write(&config_path, "level = \"loud\"").unwrap();
+        assert!(wait_for(
+            || logged(&logger, &log_path).contains("ERROR swing - failed to reload config from")
+        ));
+        assert!(logged(&logger, &log_path).contains(&format!(
+            "keeping the current config: invalid config file {} at `level`",
+            config_path.display()
+        )));
+        assert!(logger.enabled(&debug));
+
+        // changes after the watcher is dropped are ignored (`warn` changes the file's
+        // size, so the change would be noticed even with coarse modification times)
+        drop(watcher);
+        let before = logged(&logger, &log_path);
+        fs::write(&config_path, config("warn")).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        assert!(logger.enabled(&debug));
+        assert_eq!(logged(&logger, &log_path), before);
+    }
+}
@@ -1,11 +1,152 @@
-use colored::Colorize;
-use log::Level;
+use crate::file::{FileConfig, FileWriter};
+use crate::journal;
+use crate::socket::DatagramWriter;
+use crate::term::{self, ColorSupport};
+use log::{Level, Record};
+use serde::Deserialize;
 use std::io;
-use std::io::Write;
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
 use std::sync::Mutex;
 
+/// Output destination for log records
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Destination {
+    /// write logs to `stdout`/`stderr`, split according to `Config::use_stderr`
+    Console,
+    /// write logs to a file, with optional rotation (records are never colored)
+    File(FileConfig),
+    /// send each record as a datagram to a syslog daemon's Unix socket (usually
+    /// `SYSLOG_SOCKET`, i.e. `/dev/log`), for use with the `Rfc5424` and `Rfc3164`
+    /// record formats (records are never colored)
+    Syslog(PathBuf),
+    /// send each record to systemd-journald's native protocol socket (usually
+    /// `JOURNALD_SOCKET`), with the level, target, source location, and structured
+    /// key-values as journal fields (records are never colored), and entries too large for
+    /// a datagram passed to journald as sealed memory files
+    Journald(PathBuf),
+}
+
+/// Formatted log record, encoded for its destination
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Payload {
+    /// text message
+    Text(String),
+    /// binary message (e.g. a journal entry)
+    Bytes(Vec<u8>),
+}
+
+impl Payload {
+    /// Get the message as text, replacing invalid UTF-8
+    fn into_text(self) -> String {
+        match self {
+            Payload::Text(s) => s,
+            Payload::Bytes(b) => String::from_utf8_lossy(&b).into_owned(),
+        }
+    }
+
+    /// Get the message as bytes
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Payload::Text(s) => s.as_bytes(),
+            Payload::Bytes(b) => b,
+        }
+    }
+}
+
+/// Write logs to an output destination
+pub enum LogWriter {
+    /// write to stdout/stderr
+    Console(ConsoleWriter),
+    /// write to a log file
+    File(FileWriter),
+    /// send to a Unix datagram socket
+    Datagram(DatagramWriter),
+    /// send journal entries to journald's Unix datagram socket
+    Journal(DatagramWriter),
+}
+
+impl LogWriter {
+    /// Create a new LogWriter
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - where to write logs
+    /// * `use_stderr` - switch for enabling log splitting to `stderr` for console output
+    /// * `color_support` - colors supported by console output (`None` detects them for
+    ///   each stream)
+    pub fn new(
+        destination: Destination,
+        use_stderr: bool,
+        color_support: Option<ColorSupport>,
+    ) -> LogWriter {
+        match destination {
+            Destination::Console => {
+                LogWriter::Console(ConsoleWriter::new(use_stderr, color_support))
+            }
+            Destination::File(config) => LogWriter::File(FileWriter::new(config)),
+            Destination::Syslog(path) => LogWriter::Datagram(DatagramWriter::new(path)),
+            Destination::Journald(path) => LogWriter::Journal(DatagramWriter::journal(path)),
+        }
+    }
+
+    /// Check if logs written by this writer may contain ANSI color codes
+    pub fn supports_color(&self) -> bool {
+        matches!(self, LogWriter::Console(_))
+    }
+
+    /// Get the colors supported by the stream that records at `level` are written to
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - level of a log record
+    pub fn color_support(&self, level: Level) -> ColorSupport {
+        match self {
+            LogWriter::Console(w) => w.color_support(level),
+            _ => ColorSupport::Off,
+        }
+    }
+
+    /// Encode a formatted log message for this writer's destination
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - the log record that was formatted
+    /// * `msg` - the formatted log message
+    pub fn encode(&self, record: &Record, msg: String) -> Payload {
+        match self {
+            LogWriter::Journal(_) => Payload::Bytes(journal::encode(record, &msg)),
+            _ => Payload::Text(msg),
+        }
+    }
+
+    /// Write log message to output destination
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - the log message to write
+    /// * `level` - the level of this log message
+    pub fn write(&self, msg: Payload, level: Level) {
+        match self {
+            LogWriter::Console(w) => w.write(msg.into_text(), level),
+            LogWriter::File(w) => w.write(&msg.into_text()),
+            LogWriter::Datagram(w) | LogWriter::Journal(w) => w.send(msg.as_bytes()),
+        }
+    }
+
+    /// Flush any buffered output to the destination
+    pub fn flush(&self) {
+        match self {
+            LogWriter::Console(w) => w.flush(),
+            LogWriter::File(w) => w.flush(),
+            LogWriter::Datagram(_) | LogWriter::Journal(_) => {}
+        }
+    }
+}
+
 /// Write logs to stdout/stderr with synchronization
-pub struct LogWriter {
+pub struct ConsoleWriter {
     /// switch for enabling log splitting to `stderr`
     ///
     /// - `true`: log `trace` - `info` levels to `stdout` and `warn` - `error` levels to `stderr`
@@ -18,16 +159,53 @@ pub struct LogWriter {
     stdout: io::Stdout,
     /// handle to stderr
     stderr: io::Stderr,
+    /// colors supported by stdout
+    stdout_colors: ColorSupport,
+    /// colors supported by stderr
+    stderr_colors: ColorSupport,
 }
 
-impl LogWriter {
-    /// Create a new LogWriter
-    pub fn new(use_stderr: bool) -> LogWriter {
-        LogWriter {
+impl ConsoleWriter {
+    /// Create a new ConsoleWriter
+    ///
+    /// # Arguments
+    ///
+    /// * `use_stderr` - switch for enabling log splitting to `stderr`
+    /// * `color_support` - colors supported by stdout and stderr (`None` detects them for
+    ///   each stream)
+    pub fn new(use_stderr: bool, color_support: Option<ColorSupport>) -> ConsoleWriter {
+        let stdout = io::stdout();
+        let stderr = io::stderr();
+
+        ConsoleWriter {
             use_stderr,
             write_mtx: Mutex::new(()),
-            stdout: io::stdout(),
-            stderr: io::stderr(),
+            stdout_colors: color_support.unwrap_or_else(|| term::detect(stdout.is_terminal())),
+            stderr_colors: color_support.unwrap_or_else(|| term::detect(stderr.is_terminal())),
+            stdout,
+            stderr,
+        }
+    }
+
+    /// Check if records at `level` are written to stderr
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - level of a log record
+    fn uses_stderr(&self, level: Level) -> bool {
+        self.use_stderr && level <= Level::Warn
+    }
+
+    /// Get the colors supported by the stream that records at `level` are written to
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - level of a log record
+    pub fn color_support(&self, level: Level) -> ColorSupport {
+        if self.uses_stderr(level) {
+            self.stderr_colors
+        } else {
+            self.stdout_colors
         }
     }
 
@@ -46,18 +224,17 @@ impl LogWriter {
         // interleaved with stderr
         let _lk = self.write_mtx.lock().unwrap();
 
-        match level {
-            Level::Warn | Level::Error => {
-                if self.use_stderr {
-                    let _ = writeln!(self.stderr.lock(), "{}", msg.bold());
-                } else {
-                    let _ = writeln!(self.stdout.lock(), "{}", msg.bold());
-                }
-            }
-            _ => {
-                let _ = writeln!(self.stdout.lock(), "{}", msg);
-            }
-        };
+        if self.uses_stderr(level) {
+            let _ = writeln!(self.stderr.lock(), "{}", msg);
+        } else {
+            let _ = writeln!(self.stdout.lock(), "{}", msg);
+        }
+    }
+
+    /// Flush stdout and stderr
+    pub fn flush(&self) {
+        let _ = self.stdout.lock().flush();
+        let _ = self.stderr.lock().flush();
     }
 }
 
@@ -67,7 +244,7 @@ mod tests {
 
     #[test]
     fn write_handles_empty_msg() {
-        let levels = vec![
+        let levels = [
             Level::Trace,
             Level::Debug,
             Level::Info,
@@ -76,17 +253,39 @@ mod tests {
         ];
 
         // using stderr
-        let writer = LogWriter::new(true);
+        let writer = ConsoleWriter::new(true, None);
 
         for level in levels.iter() {
             writer.write("".to_string(), *level);
         }
 
         // not using stderr
-        let writer = LogWriter::new(false);
+        let writer = ConsoleWriter::new(false, None);
 
         for level in levels.iter() {
             writer.write("".to_string(), *level);
         }
+        writer.flush();
+    }
+
+    #[test]
+    fn color_support_follows_level_stream() {
+        let writer = ConsoleWriter {
+            stdout_colors: ColorSupport::Ansi256,
+            stderr_colors: ColorSupport::Off,
+            ..ConsoleWriter::new(true, None)
+        };
+
+        assert_eq!(writer.color_support(Level::Info), ColorSupport::Ansi256);
+        assert_eq!(writer.color_support(Level::Warn), ColorSupport::Off);
+
+        let writer = ConsoleWriter {
+            use_stderr: false,
+            ..writer
+        };
+        assert_eq!(writer.color_support(Level::Error), ColorSupport::Ansi256);
+
+        let writer = LogWriter::new(Destination::Console, true, Some(ColorSupport::Ansi16));
+        assert_eq!(writer.color_support(Level::Error), ColorSupport::Ansi16);
     }
 }